@@ -0,0 +1,123 @@
+//! Synthetic-tree benchmark for `scan::walk_supported_files`.
+//!
+//! Run with `cargo bench --bench scan_walk`. The tree shape is controlled through
+//! `RUSTREADER_BENCH_DIRS`, `RUSTREADER_BENCH_FILES` and `RUSTREADER_BENCH_DEPTH`; set
+//! `RUSTREADER_BENCH_MIN_SPEEDUP` (e.g. `1.5`) to fail when the parallel walk regresses.
+
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use app_lib::scan::{self, ScanFile, ScanOptions};
+use common::CollectSink;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+const SAMPLE_NAMES: &[&str] = &[
+  "README.md",
+  "notes.txt",
+  "diagram.drawio",
+  "photo.png",
+  "slides.ppt.md",
+  "map.mm.md",
+  "report.pdf",
+  "data.csv",
+  "main.rs",
+  "archive.bin",
+];
+
+fn env_usize(name: &str, default: usize) -> usize {
+  std::env::var(name)
+    .ok()
+    .and_then(|value| value.trim().parse().ok())
+    .unwrap_or(default)
+}
+
+fn build_fixture(
+  root: &Path,
+  dirs: usize,
+  files_per_dir: usize,
+  depth: usize,
+) -> std::io::Result<()> {
+  for index in 0..dirs {
+    let mut dir = root.to_path_buf();
+    for level in 0..depth.max(1) {
+      dir.push(format!("d{}_{}", level, (index >> level) % 8));
+    }
+    dir.push(format!("leaf{index}"));
+    std::fs::create_dir_all(&dir)?;
+
+    for file_index in 0..files_per_dir {
+      let name = SAMPLE_NAMES[(index + file_index) % SAMPLE_NAMES.len()];
+      std::fs::write(dir.join(format!("{file_index}_{name}")), b"x")?;
+    }
+  }
+  Ok(())
+}
+
+fn run_walk(root: &Path, threads: usize) -> (Duration, Vec<ScanFile>) {
+  let cancel_flag = AtomicBool::new(false);
+  let sink = CollectSink::default();
+  let started = Instant::now();
+  scan::walk_supported_files(root, &ScanOptions::default(), threads, &cancel_flag, &sink)
+    .expect("walk was not cancelled");
+  let elapsed = started.elapsed();
+
+  (elapsed, sink.into_files())
+}
+
+fn best_of(root: &Path, threads: usize, rounds: usize) -> (Duration, Vec<ScanFile>) {
+  let mut best: Option<(Duration, Vec<ScanFile>)> = None;
+  for _ in 0..rounds.max(1) {
+    let (elapsed, files) = run_walk(root, threads);
+    if best.as_ref().map_or(true, |(current, _)| elapsed < *current) {
+      best = Some((elapsed, files));
+    }
+  }
+  best.expect("at least one round")
+}
+
+fn main() {
+  let dirs = env_usize("RUSTREADER_BENCH_DIRS", 4000);
+  let files_per_dir = env_usize("RUSTREADER_BENCH_FILES", 20);
+  let depth = env_usize("RUSTREADER_BENCH_DEPTH", 4);
+  let rounds = env_usize("RUSTREADER_BENCH_ROUNDS", 3);
+  let threads = scan::default_scan_threads();
+
+  let root: PathBuf =
+    std::env::temp_dir().join(format!("rustreader-scan-bench-{}", std::process::id()));
+  let _ = std::fs::remove_dir_all(&root);
+  build_fixture(&root, dirs, files_per_dir, depth).expect("failed to build synthetic tree");
+  let root = root.canonicalize().expect("fixture root");
+
+  // Warm the page cache so both variants measure the walk rather than cold disk reads.
+  let _ = run_walk(&root, threads);
+
+  let (sequential, sequential_files) = best_of(&root, 1, rounds);
+  let (parallel, parallel_files) = best_of(&root, threads, rounds);
+  let _ = std::fs::remove_dir_all(&root);
+
+  assert_eq!(sequential_files, parallel_files, "parallel walk must match the sequential output");
+
+  let speedup = sequential.as_secs_f64() / parallel.as_secs_f64().max(f64::EPSILON);
+  println!(
+    "scan_walk: {} dirs x {} files, {} matched; 1 thread {:?}, {} threads {:?}, speedup {:.2}x",
+    dirs,
+    files_per_dir,
+    parallel_files.len(),
+    sequential,
+    threads,
+    parallel,
+    speedup
+  );
+
+  if let Some(min_speedup) = std::env::var("RUSTREADER_BENCH_MIN_SPEEDUP")
+    .ok()
+    .and_then(|value| value.trim().parse::<f64>().ok())
+  {
+    if speedup < min_speedup {
+      eprintln!("scan_walk: speedup {speedup:.2}x is below the required {min_speedup:.2}x");
+      std::process::exit(1);
+    }
+  }
+}
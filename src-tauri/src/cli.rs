@@ -0,0 +1,347 @@
+//! Command-line arguments: the desktop app's options and the headless subcommands.
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Starts an independent window and process even when rustreader is already running.
+pub const NEW_WINDOW_FLAG: &str = "--new-window";
+
+const OPEN_USAGE: &str = "用法: rustreader [选项] [路径...]";
+const INDEX_USAGE: &str = "用法: rustreader index [目录] [--out 文件]";
+const WATCH_USAGE: &str = "用法: rustreader watch [目录] [--out 文件]";
+const EXPORT_USAGE: &str = "用法: rustreader export <源目录> <导出目录> [--site-name 名称]";
+
+const OPEN_HELP: &str = "\
+用法: rustreader [选项] [路径...]
+      rustreader <子命令> [参数]
+
+打开文件或文件夹:
+  <路径>, -o <路径>, --open <路径>, --path <路径>
+                        启动后打开的文件或文件夹；给出多个时合并为一个
+                        工作区，每个路径是一个顶层节点
+  <路径>#<锚点>         打开文件并跳到该锚点，如 guide.md#install
+  --select <相对路径>   打开文件夹后选中其中的文件；打开多个路径时
+                        以顶层节点名开头
+  --anchor <锚点>       预览后跳到该标题，接受标题的锚点名或标题文字
+  --site-name <名称>    窗口标题显示为 rustreader - <名称>
+  --new-window          不交给已在运行的 rustreader，另开窗口与进程
+  -h, --help            显示帮助
+  -V, --version         显示版本
+
+子命令（不启动窗口）:
+  index                 生成与 ui/generate.py 一致的 index.json
+  watch                 持续监听目录并更新 index.json
+  export                导出为静态站点
+  help <子命令>         显示子命令的帮助";
+
+const INDEX_HELP: &str = "\
+用法: rustreader index [目录] [--out 文件]
+
+扫描目录（默认当前目录）并写出 index.json。
+
+选项:
+  --out <文件>          写到指定文件，而不是目录下的 index.json
+  -h, --help            显示帮助";
+
+const WATCH_HELP: &str = "\
+用法: rustreader watch [目录] [--out 文件]
+
+先生成一次 index.json，然后持续监听目录（默认当前目录）并在文件增删时更新。
+
+选项:
+  --out <文件>          写到指定文件，而不是目录下的 index.json
+  -h, --help            显示帮助";
+
+const EXPORT_HELP: &str = "\
+用法: rustreader export <源目录> <导出目录> [--site-name 名称]
+
+把界面、源目录中可预览的文件和 index.json 写入空的导出目录。
+
+选项:
+  --site-name <名称>    页面标题与 brandText（默认“文件浏览器”）
+  -h, --help            显示帮助";
+
+const OPEN_OPTIONS: &[&str] = &[
+  "-h",
+  "--help",
+  "-V",
+  "--version",
+  "-o",
+  "--open",
+  "--path",
+  "--select",
+  "--anchor",
+  "--site-name",
+  NEW_WINDOW_FLAG,
+];
+const INDEX_OPTIONS: &[&str] = &["-h", "--help", "--out"];
+const EXPORT_OPTIONS: &[&str] = &["-h", "--help", "--site-name"];
+
+/// What the process was asked to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+  /// Start the desktop app.
+  Open(OpenArgs),
+  Index(IndexArgs),
+  Watch(IndexArgs),
+  Export(ExportArgs),
+  /// Print this help text and exit.
+  Help(&'static str),
+  Version,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenArgs {
+  /// The files and folders to open, as given; several are opened as one workspace.
+  pub targets: Vec<String>,
+  /// The document to preselect, relative to the opened folder.
+  pub select: Option<String>,
+  /// The heading to scroll to once the document is shown.
+  pub anchor: Option<String>,
+  pub site_name: Option<String>,
+  pub new_window: bool,
+}
+
+/// Arguments of `index` and `watch`; the directory defaults to the current one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexArgs {
+  pub root: Option<PathBuf>,
+  pub out: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportArgs {
+  pub src: PathBuf,
+  pub dest: PathBuf,
+  pub site_name: Option<String>,
+}
+
+/// Parses the arguments after the program name. Errors carry the message and the usage line
+/// to print.
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Command, String> {
+  let mut args = args.into_iter().peekable();
+  let subcommand = args
+    .peek()
+    .and_then(|arg| arg.to_str())
+    .filter(|arg| matches!(*arg, "index" | "watch" | "export" | "help"))
+    .map(str::to_string);
+  let Some(subcommand) = subcommand else {
+    return parse_open(args);
+  };
+  args.next();
+  match subcommand.as_str() {
+    "index" => parse_index(args, Command::Index, INDEX_USAGE, INDEX_HELP),
+    "watch" => parse_index(args, Command::Watch, WATCH_USAGE, WATCH_HELP),
+    "export" => parse_export(args),
+    _ => parse_help(args),
+  }
+}
+
+fn parse_open(args: impl Iterator<Item = OsString>) -> Result<Command, String> {
+  let mut parsed = OpenArgs::default();
+  let mut iter = args;
+  while let Some(arg) = iter.next() {
+    let arg_str = arg.to_string_lossy();
+    let arg_str = arg_str.trim();
+    if arg_str.is_empty() {
+      continue;
+    }
+
+    if arg_str == "--" {
+      parsed.targets.extend(iter.by_ref().filter_map(|value| non_empty(&value)));
+      break;
+    }
+
+    // macOS adds a process serial number when the app is started from Finder.
+    if arg_str.starts_with("-psn_") {
+      continue;
+    }
+
+    if !arg_str.starts_with('-') {
+      parsed.targets.push(arg_str.to_string());
+      continue;
+    }
+
+    match split_option(arg_str) {
+      ("-h" | "--help", None) => return Ok(Command::Help(OPEN_HELP)),
+      ("-V" | "--version", None) => return Ok(Command::Version),
+      (option @ ("-o" | "--open" | "--path"), inline) => {
+        let value = take_value(option, inline, &mut iter, OPEN_USAGE)?;
+        parsed.targets.extend(non_empty(&value));
+      }
+      (option @ "--select", inline) => {
+        let value = take_value(option, inline, &mut iter, OPEN_USAGE)?;
+        parsed.select = non_empty(&value);
+      }
+      (option @ "--anchor", inline) => {
+        let value = take_value(option, inline, &mut iter, OPEN_USAGE)?;
+        // `#install` is accepted as well, as it appears in a link.
+        let value = value.to_string_lossy().trim_start_matches('#').to_string();
+        parsed.anchor = non_empty(&OsString::from(value));
+      }
+      (option @ "--site-name", inline) => {
+        let value = take_value(option, inline, &mut iter, OPEN_USAGE)?;
+        parsed.site_name = non_empty(&value);
+      }
+      (NEW_WINDOW_FLAG, None) => parsed.new_window = true,
+      _ => return Err(unknown_option(arg_str, OPEN_OPTIONS, OPEN_USAGE)),
+    }
+  }
+  Ok(Command::Open(parsed))
+}
+
+/// Parses `index` or `watch`, which take the same arguments; `command` builds the result.
+fn parse_index(
+  args: impl Iterator<Item = OsString>,
+  command: fn(IndexArgs) -> Command,
+  usage: &'static str,
+  help: &'static str,
+) -> Result<Command, String> {
+  let mut parsed = IndexArgs::default();
+  let mut positional = Vec::new();
+  let mut iter = args;
+  while let Some(arg) = iter.next() {
+    let arg_str = arg.to_string_lossy().into_owned();
+    if arg_str == "--" {
+      positional.extend(iter.by_ref());
+      break;
+    }
+    if !arg_str.starts_with('-') {
+      positional.push(arg);
+      continue;
+    }
+    match split_option(&arg_str) {
+      ("-h" | "--help", None) => return Ok(Command::Help(help)),
+      (option @ "--out", inline) => {
+        parsed.out = Some(PathBuf::from(take_value(option, inline, &mut iter, usage)?));
+      }
+      _ => return Err(unknown_option(&arg_str, INDEX_OPTIONS, usage)),
+    }
+  }
+
+  let mut positional = positional.into_iter();
+  parsed.root = positional.next().map(PathBuf::from);
+  if let Some(extra) = positional.next() {
+    return Err(usage_error(format!("多余的参数: {}", extra.to_string_lossy()), usage));
+  }
+  Ok(command(parsed))
+}
+
+fn parse_export(args: impl Iterator<Item = OsString>) -> Result<Command, String> {
+  let mut site_name = None;
+  let mut positional = Vec::new();
+  let mut iter = args;
+  while let Some(arg) = iter.next() {
+    let arg_str = arg.to_string_lossy().into_owned();
+    if arg_str == "--" {
+      positional.extend(iter.by_ref());
+      break;
+    }
+    if !arg_str.starts_with('-') {
+      positional.push(arg);
+      continue;
+    }
+    match split_option(&arg_str) {
+      ("-h" | "--help", None) => return Ok(Command::Help(EXPORT_HELP)),
+      (option @ "--site-name", inline) => {
+        let value = take_value(option, inline, &mut iter, EXPORT_USAGE)?;
+        site_name = non_empty(&value);
+      }
+      _ => return Err(unknown_option(&arg_str, EXPORT_OPTIONS, EXPORT_USAGE)),
+    }
+  }
+
+  let mut positional = positional.into_iter().map(PathBuf::from);
+  match (positional.next(), positional.next(), positional.next()) {
+    (Some(src), Some(dest), None) => Ok(Command::Export(ExportArgs {
+      src,
+      dest,
+      site_name,
+    })),
+    (Some(_), Some(_), Some(extra)) => {
+      Err(usage_error(format!("多余的参数: {}", extra.display()), EXPORT_USAGE))
+    }
+    _ => Err(usage_error("需要源目录和导出目录".to_string(), EXPORT_USAGE)),
+  }
+}
+
+fn parse_help(mut args: impl Iterator<Item = OsString>) -> Result<Command, String> {
+  let Some(topic) = args.next() else {
+    return Ok(Command::Help(OPEN_HELP));
+  };
+  match topic.to_string_lossy().as_ref() {
+    "index" => Ok(Command::Help(INDEX_HELP)),
+    "watch" => Ok(Command::Help(WATCH_HELP)),
+    "export" => Ok(Command::Help(EXPORT_HELP)),
+    other => Err(usage_error(
+      format!("没有名为 {} 的子命令", other),
+      "用法: rustreader help [index|watch|export]",
+    )),
+  }
+}
+
+/// Splits `guide.md#install` into the path and the anchor. The anchor is the text after the
+/// last `#` and cannot contain a path separator, so `notes#2/a.md` stays one path.
+pub fn split_anchor(target: &str) -> Option<(&str, &str)> {
+  let (path, anchor) = target.rsplit_once('#')?;
+  if path.is_empty() || anchor.is_empty() || anchor.contains(['/', '\\']) {
+    return None;
+  }
+  Some((path, anchor))
+}
+
+/// Splits `--name=value` into the option and its inline value; short options never carry one.
+fn split_option(arg: &str) -> (&str, Option<&str>) {
+  if !arg.starts_with("--") {
+    return (arg, None);
+  }
+  match arg.split_once('=') {
+    Some((option, value)) => (option, Some(value)),
+    None => (arg, None),
+  }
+}
+
+/// The value of `option`: the text after `=` when given inline, the next argument otherwise.
+fn take_value(
+  option: &str,
+  inline: Option<&str>,
+  iter: &mut impl Iterator<Item = OsString>,
+  usage: &str,
+) -> Result<OsString, String> {
+  if let Some(value) = inline {
+    return Ok(OsString::from(value));
+  }
+  iter
+    .next()
+    .ok_or_else(|| usage_error(format!("{} 缺少参数值", option), usage))
+}
+
+/// Blank values count as not given, as they always have.
+fn non_empty(value: &OsString) -> Option<String> {
+  let value = value.to_string_lossy();
+  let value = value.trim();
+  (!value.is_empty()).then(|| value.to_string())
+}
+
+/// Names the known option `arg` most likely meant, e.g. `--site-name` for `--sitename`.
+fn unknown_option(arg: &str, known: &[&str], usage: &str) -> String {
+  let (option, _) = split_option(arg);
+  let normalized = normalize_option(option);
+  let message = match known.iter().find(|known| normalize_option(known) == normalized) {
+    Some(known) => format!("无法识别的选项: {}（是否想输入 {}？）", option, known),
+    None => format!("无法识别的选项: {}", option),
+  };
+  usage_error(message, usage)
+}
+
+fn normalize_option(option: &str) -> String {
+  option
+    .chars()
+    .filter(char::is_ascii_alphanumeric)
+    .map(|c| c.to_ascii_lowercase())
+    .collect()
+}
+
+fn usage_error(message: String, usage: &str) -> String {
+  format!("{}\n{}\n使用 --help 查看全部选项", message, usage)
+}
@@ -0,0 +1,318 @@
+use crate::pdf;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// XML parts are read whole; anything larger is not a document worth reading as text.
+const MAX_PART_LEN: u64 = 64 * 1024 * 1024;
+/// Columns past `XFD`, the last one Excel allows, are not padded out.
+const MAX_SHEET_COLUMNS: usize = 16_384;
+pub(crate) const DOCUMENT_CATEGORIES: &[&str] = &["word", "excel", "slides", "pdf"];
+
+type Archive = zip::ZipArchive<File>;
+
+/// Plain text of a Word, Excel, PowerPoint or PDF file. Returns `Ok(None)` for other
+/// categories and for files that are not readable packages of the expected kind.
+pub fn extract_text(path: &Path, category: &str) -> std::io::Result<Option<String>> {
+  match category {
+    "word" | "excel" | "slides" => {
+      let Ok(mut archive) = zip::ZipArchive::new(File::open(path)?) else {
+        return Ok(None);
+      };
+      match category {
+        "word" => word_text(&mut archive),
+        "excel" => excel_text(&mut archive),
+        _ => slides_text(&mut archive),
+      }
+    }
+    "pdf" => pdf::extract_text(path),
+    _ => Ok(None),
+  }
+}
+
+pub(crate) fn is_document_category(category: &str) -> bool {
+  DOCUMENT_CATEGORIES.contains(&category)
+}
+
+fn read_part(archive: &mut Archive, name: &str) -> std::io::Result<Option<String>> {
+  let Ok(entry) = archive.by_name(name) else {
+    return Ok(None);
+  };
+  let mut content = String::new();
+  entry.take(MAX_PART_LEN).read_to_string(&mut content)?;
+  Ok(Some(content))
+}
+
+fn word_text(archive: &mut Archive) -> std::io::Result<Option<String>> {
+  Ok(read_part(archive, "word/document.xml")?.map(|xml| paragraph_text(&xml)))
+}
+
+/// Slides in file-name order (`slide1.xml`, `slide2.xml`, ...), which matches the deck order
+/// for everything but decks whose slides were reordered after creation.
+fn slides_text(archive: &mut Archive) -> std::io::Result<Option<String>> {
+  let mut slides: Vec<(u32, String)> = archive
+    .file_names()
+    .filter_map(|name| {
+      let number = name.strip_prefix("ppt/slides/slide")?.strip_suffix(".xml")?;
+      Some((number.parse().ok()?, name.to_string()))
+    })
+    .collect();
+  if slides.is_empty() {
+    return Ok(None);
+  }
+  slides.sort();
+
+  let mut text = String::new();
+  for (_, name) in slides {
+    if let Some(xml) = read_part(archive, &name)? {
+      text.push_str(&paragraph_text(&xml));
+      text.push('\n');
+    }
+  }
+  Ok(Some(text))
+}
+
+/// Every sheet as its name followed by one tab-separated line per row.
+fn excel_text(archive: &mut Archive) -> std::io::Result<Option<String>> {
+  let shared = match read_part(archive, "xl/sharedStrings.xml")? {
+    Some(xml) => shared_strings(&xml),
+    None => Vec::new(),
+  };
+  let sheets = workbook_sheets(archive)?;
+  if sheets.is_empty() {
+    return Ok(None);
+  }
+
+  let mut text = String::new();
+  for (name, part) in sheets {
+    let Some(xml) = read_part(archive, &part)? else {
+      continue;
+    };
+    text.push_str(&name);
+    text.push('\n');
+    sheet_text(&xml, &shared, &mut text);
+    text.push('\n');
+  }
+  Ok(Some(text))
+}
+
+/// Sheet names and part paths in workbook order, resolved through the workbook relationships.
+fn workbook_sheets(archive: &mut Archive) -> std::io::Result<Vec<(String, String)>> {
+  let targets = match read_part(archive, "xl/_rels/workbook.xml.rels")? {
+    Some(xml) => relationship_targets(&xml),
+    None => HashMap::new(),
+  };
+  let Some(workbook) = read_part(archive, "xl/workbook.xml")? else {
+    return Ok(Vec::new());
+  };
+
+  let mut sheets = Vec::new();
+  let mut reader = Reader::from_str(&workbook);
+  loop {
+    match reader.read_event() {
+      Ok(Event::Start(element) | Event::Empty(element))
+        if element.local_name().as_ref() == b"sheet" =>
+      {
+        let name = attribute(&element, |key| key == b"name").unwrap_or_default();
+        let target = attribute(&element, |key| key.ends_with(b":id"))
+          .and_then(|id| targets.get(&id).cloned());
+        if let Some(target) = target {
+          sheets.push((name, target));
+        }
+      }
+      Ok(Event::Eof) | Err(_) => break,
+      _ => {}
+    }
+  }
+  Ok(sheets)
+}
+
+/// Relationship ids to part paths, made absolute within the `xl/` folder.
+fn relationship_targets(xml: &str) -> HashMap<String, String> {
+  let mut targets = HashMap::new();
+  let mut reader = Reader::from_str(xml);
+  loop {
+    match reader.read_event() {
+      Ok(Event::Start(element) | Event::Empty(element))
+        if element.local_name().as_ref() == b"Relationship" =>
+      {
+        let id = attribute(&element, |key| key == b"Id");
+        let target = attribute(&element, |key| key == b"Target");
+        if let (Some(id), Some(target)) = (id, target) {
+          let target = match target.strip_prefix('/') {
+            Some(absolute) => absolute.to_string(),
+            None => format!("xl/{}", target),
+          };
+          targets.insert(id, target);
+        }
+      }
+      Ok(Event::Eof) | Err(_) => break,
+      _ => {}
+    }
+  }
+  targets
+}
+
+fn shared_strings(xml: &str) -> Vec<String> {
+  let mut strings = Vec::new();
+  let mut current = String::new();
+  let mut in_text = false;
+  // Phonetic runs repeat the reading of East Asian text and would duplicate it.
+  let mut in_phonetic = false;
+  let mut reader = Reader::from_str(xml);
+  loop {
+    match reader.read_event() {
+      Ok(Event::Start(element)) => match element.local_name().as_ref() {
+        b"si" => current.clear(),
+        b"rPh" => in_phonetic = true,
+        b"t" => in_text = !in_phonetic,
+        _ => {}
+      },
+      Ok(Event::End(element)) => match element.local_name().as_ref() {
+        b"si" => strings.push(std::mem::take(&mut current)),
+        b"rPh" => in_phonetic = false,
+        b"t" => in_text = false,
+        _ => {}
+      },
+      Ok(Event::Empty(element)) if element.local_name().as_ref() == b"si" => {
+        strings.push(String::new());
+      }
+      Ok(Event::Eof) | Err(_) => break,
+      Ok(event) if in_text => push_event_text(&event, &mut current),
+      _ => {}
+    }
+  }
+  strings
+}
+
+fn sheet_text(xml: &str, shared: &[String], text: &mut String) {
+  let mut row: Vec<String> = Vec::new();
+  let mut cell_type = String::new();
+  let mut cell_column = None;
+  let mut value = String::new();
+  let mut in_value = false;
+  let mut reader = Reader::from_str(xml);
+  loop {
+    match reader.read_event() {
+      Ok(Event::Start(element)) => match element.local_name().as_ref() {
+        b"row" => row.clear(),
+        b"c" => {
+          cell_type = attribute(&element, |key| key == b"t").unwrap_or_default();
+          cell_column = attribute(&element, |key| key == b"r").and_then(|r| column_index(&r));
+          value.clear();
+        }
+        b"v" | b"t" => in_value = true,
+        _ => {}
+      },
+      Ok(Event::End(element)) => match element.local_name().as_ref() {
+        b"v" | b"t" => in_value = false,
+        b"c" => {
+          let cell = match cell_type.as_str() {
+            "s" => value
+              .trim()
+              .parse::<usize>()
+              .ok()
+              .and_then(|index| shared.get(index).cloned())
+              .unwrap_or_default(),
+            _ => std::mem::take(&mut value),
+          };
+          // Empty cells are usually left out, so the reference says where this one goes.
+          if let Some(column) = cell_column {
+            if column > row.len() {
+              row.resize(column, String::new());
+            }
+          }
+          row.push(cell);
+        }
+        b"row" => {
+          let cells: Vec<&str> = row.iter().map(|cell| cell.trim()).collect();
+          let last = cells.iter().rposition(|cell| !cell.is_empty());
+          if let Some(last) = last {
+            text.push_str(&cells[..=last].join("\t"));
+            text.push('\n');
+          }
+        }
+        _ => {}
+      },
+      Ok(Event::Eof) | Err(_) => break,
+      Ok(event) if in_value => push_event_text(&event, &mut value),
+      _ => {}
+    }
+  }
+}
+
+/// The zero-based column of a cell reference like `C7`.
+fn column_index(reference: &str) -> Option<usize> {
+  let letters = reference.bytes().take_while(u8::is_ascii_alphabetic);
+  let mut column = 0usize;
+  for letter in letters {
+    column = column * 26 + usize::from(letter.to_ascii_uppercase() - b'A') + 1;
+    if column > MAX_SHEET_COLUMNS {
+      return None;
+    }
+  }
+  column.checked_sub(1)
+}
+
+/// Text of WordprocessingML or DrawingML: the `t` runs, one line per `p` paragraph.
+fn paragraph_text(xml: &str) -> String {
+  let mut text = String::new();
+  let mut in_text = false;
+  let mut reader = Reader::from_str(xml);
+  loop {
+    match reader.read_event() {
+      Ok(Event::Start(element)) if element.local_name().as_ref() == b"t" => in_text = true,
+      Ok(Event::End(element)) => match element.local_name().as_ref() {
+        b"t" => in_text = false,
+        b"p" => text.push('\n'),
+        _ => {}
+      },
+      Ok(Event::Empty(element)) => match element.local_name().as_ref() {
+        // Tab stops in paragraph properties are also `tab` elements, but carry attributes.
+        b"tab" if element.attributes().next().is_none() => text.push('\t'),
+        b"br" | b"cr" => text.push('\n'),
+        _ => {}
+      },
+      Ok(Event::Eof) | Err(_) => break,
+      Ok(event) if in_text => push_event_text(&event, &mut text),
+      _ => {}
+    }
+  }
+  text
+}
+
+fn push_event_text(event: &Event, text: &mut String) {
+  match event {
+    Event::Text(content) => {
+      if let Ok(content) = content.decode() {
+        text.push_str(&content);
+      }
+    }
+    Event::CData(content) => {
+      if let Ok(content) = content.decode() {
+        text.push_str(&content);
+      }
+    }
+    Event::GeneralRef(reference) => {
+      if let Ok(Some(ch)) = reference.resolve_char_ref() {
+        text.push(ch);
+      } else if let Ok(name) = reference.decode() {
+        if let Some(resolved) = quick_xml::escape::resolve_predefined_entity(&name) {
+          text.push_str(resolved);
+        }
+      }
+    }
+    _ => {}
+  }
+}
+
+fn attribute(element: &BytesStart, matches_key: impl Fn(&[u8]) -> bool) -> Option<String> {
+  element
+    .attributes()
+    .flatten()
+    .find(|attribute| matches_key(attribute.key.as_ref()))
+    .and_then(|attribute| attribute.unescape_value().ok().map(|value| value.into_owned()))
+}
@@ -0,0 +1,482 @@
+use globset::{GlobBuilder, GlobMatcher};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// Every category the preview knows how to render, in the order `index.json` lists them.
+pub(crate) const CATEGORY_ORDER: &[&str] = &[
+  "images", "video", "audio", "markdown", "mindmap", "drawio", "pdf", "word", "excel", "text",
+  "code", "slides", "marpit",
+];
+
+/// Compound suffixes are checked before plain extensions, so `a.mm.md` is a mind map.
+const BUILTIN_SUFFIXES: &[(&str, &str)] = &[(".mm.md", "mindmap"), (".ppt.md", "marpit")];
+
+const BUILTIN_EXTENSIONS: &[(&str, &str)] = &[
+  ("png", "images"),
+  ("jpg", "images"),
+  ("jpeg", "images"),
+  ("gif", "images"),
+  ("webp", "images"),
+  ("mp4", "video"),
+  ("webm", "video"),
+  ("ogv", "video"),
+  ("m4v", "video"),
+  ("mp3", "audio"),
+  ("wav", "audio"),
+  ("m4a", "audio"),
+  ("ogg", "audio"),
+  ("oga", "audio"),
+  ("flac", "audio"),
+  ("aac", "audio"),
+  ("md", "markdown"),
+  ("markdown", "markdown"),
+  ("drawio", "drawio"),
+  ("pdf", "pdf"),
+  ("docx", "word"),
+  ("docm", "word"),
+  ("dotx", "word"),
+  ("dotm", "word"),
+  ("xlsx", "excel"),
+  ("xlsm", "excel"),
+  ("xltx", "excel"),
+  ("xltm", "excel"),
+  ("txt", "text"),
+  ("pptx", "slides"),
+  ("pptm", "slides"),
+  ("potx", "slides"),
+  ("potm", "slides"),
+  ("ppsx", "slides"),
+  ("ppsm", "slides"),
+];
+
+/// Source file extensions and their highlight.js language ids; all land in `code`.
+const BUILTIN_CODE_EXTENSIONS: &[(&str, &str)] = &[
+  ("rs", "rust"),
+  ("py", "python"),
+  ("pyi", "python"),
+  ("pyw", "python"),
+  ("js", "javascript"),
+  ("mjs", "javascript"),
+  ("cjs", "javascript"),
+  ("jsx", "javascript"),
+  ("ts", "typescript"),
+  ("mts", "typescript"),
+  ("cts", "typescript"),
+  ("tsx", "typescript"),
+  ("json", "json"),
+  ("toml", "toml"),
+  ("yaml", "yaml"),
+  ("yml", "yaml"),
+  ("ini", "ini"),
+  ("cfg", "ini"),
+  ("sh", "bash"),
+  ("bash", "bash"),
+  ("zsh", "bash"),
+  ("ps1", "powershell"),
+  ("c", "c"),
+  ("h", "c"),
+  ("cc", "cpp"),
+  ("cpp", "cpp"),
+  ("cxx", "cpp"),
+  ("hh", "cpp"),
+  ("hpp", "cpp"),
+  ("hxx", "cpp"),
+  ("cs", "csharp"),
+  ("go", "go"),
+  ("java", "java"),
+  ("kt", "kotlin"),
+  ("kts", "kotlin"),
+  ("swift", "swift"),
+  ("rb", "ruby"),
+  ("php", "php"),
+  ("pl", "perl"),
+  ("pm", "perl"),
+  ("lua", "lua"),
+  ("r", "r"),
+  ("sql", "sql"),
+  ("css", "css"),
+  ("scss", "scss"),
+  ("less", "less"),
+  ("html", "xml"),
+  ("htm", "xml"),
+  ("xml", "xml"),
+  ("vue", "xml"),
+  ("graphql", "graphql"),
+  ("gql", "graphql"),
+  ("diff", "diff"),
+  ("patch", "diff"),
+  ("mk", "makefile"),
+  ("vb", "vbnet"),
+  ("scala", "scala"),
+  ("dart", "dart"),
+];
+
+/// Well-known file names (compared case-insensitively) that are code without an extension.
+const BUILTIN_CODE_FILE_NAMES: &[(&str, &str)] = &[
+  ("dockerfile", "dockerfile"),
+  ("containerfile", "dockerfile"),
+  ("makefile", "makefile"),
+  ("gnumakefile", "makefile"),
+  ("cmakelists.txt", "cmake"),
+  ("gemfile", "ruby"),
+  ("rakefile", "ruby"),
+  ("vagrantfile", "ruby"),
+  (".bashrc", "bash"),
+  (".bash_profile", "bash"),
+  (".zshrc", "bash"),
+  (".profile", "bash"),
+  (".editorconfig", "ini"),
+];
+
+/// Interpreters named on a `#!` line, with any version suffix (`python3.12`) removed.
+const SHEBANG_INTERPRETERS: &[(&str, &str)] = &[
+  ("sh", "bash"),
+  ("bash", "bash"),
+  ("zsh", "bash"),
+  ("ksh", "bash"),
+  ("dash", "bash"),
+  ("python", "python"),
+  ("node", "javascript"),
+  ("nodejs", "javascript"),
+  ("bun", "javascript"),
+  ("deno", "typescript"),
+  ("ruby", "ruby"),
+  ("perl", "perl"),
+  ("php", "php"),
+  ("lua", "lua"),
+  ("rscript", "r"),
+  ("pwsh", "powershell"),
+];
+
+/// A user override from the `fileTypes` list in `~/.rustreader/config`.
+///
+/// Exactly one of `extension`, `suffix` or `glob` is set. A `null` category marks matching
+/// files as unsupported, which hides a built-in type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FileTypeRule {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) extension: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) suffix: Option<String>,
+  /// Matched against the file name, or against the path below the scanned root when it
+  /// contains `/`, e.g. `docs/**/*.log`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) glob: Option<String>,
+  pub(crate) category: Option<String>,
+  /// highlight.js language id for the preview, mostly useful with the `code` category.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) language: Option<String>,
+}
+
+/// One resolved rule, listed in precedence order by `get_file_types`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileTypeEntry {
+  /// `glob`, `name`, `suffix` or `extension`.
+  kind: &'static str,
+  pattern: String,
+  category: Option<&'static str>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  language: Option<String>,
+  /// `user` or `builtin`.
+  source: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileTypesInfo {
+  categories: Vec<&'static str>,
+  rules: Vec<FileTypeEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileType {
+  category: &'static str,
+  language: Option<String>,
+}
+
+impl FileType {
+  fn builtin(category: &'static str, language: Option<&str>) -> Option<Self> {
+    Some(Self {
+      category,
+      language: language.map(str::to_string),
+    })
+  }
+}
+
+/// `None` marks a rule that hides matching files.
+type Target = Option<FileType>;
+
+struct GlobRule {
+  matcher: GlobMatcher,
+  on_name: bool,
+  target: Target,
+}
+
+/// Compound suffixes, longest first, and extensions from one source of rules.
+#[derive(Default)]
+struct SuffixRules {
+  suffixes: Vec<(String, Target)>,
+  extensions: HashMap<String, Target>,
+}
+
+impl SuffixRules {
+  /// The rule for the lower-cased file name `name_lower`, if any suffix or extension matches.
+  fn lookup(&self, name_lower: &str) -> Option<&Target> {
+    for (suffix, target) in &self.suffixes {
+      if name_lower.ends_with(suffix.as_str()) {
+        return Some(target);
+      }
+    }
+    let ext = Path::new(name_lower).extension()?.to_str()?;
+    self.extensions.get(ext)
+  }
+}
+
+/// Maps file names to preview categories. User rules of any kind come first: globs, then the
+/// longest matching compound suffix, then the extension. The built-in well-known file names,
+/// suffixes and extensions follow in the same order, so a user `txt` extension still wins over
+/// the built-in `CMakeLists.txt` name and a user `md` extension over the `.mm.md` suffix.
+///
+/// Files are looked up by virtual path, the `/`-separated path below the scanned root, which
+/// is what the frontend's copy of these rules sees as well.
+pub struct FileTypeRegistry {
+  globs: Vec<GlobRule>,
+  user: SuffixRules,
+  names: HashMap<String, Target>,
+  builtin: SuffixRules,
+  entries: Vec<FileTypeEntry>,
+}
+
+fn known_category(category: &str) -> Option<&'static str> {
+  CATEGORY_ORDER.iter().copied().find(|known| *known == category)
+}
+
+fn entry(kind: &'static str, pattern: &str, target: &Target, source: &'static str) -> FileTypeEntry {
+  FileTypeEntry {
+    kind,
+    pattern: pattern.to_string(),
+    category: target.as_ref().map(|file_type| file_type.category),
+    language: target.as_ref().and_then(|file_type| file_type.language.clone()),
+    source,
+  }
+}
+
+/// Sorts `suffixes` longest first, keeping the first rule for each suffix, and lists them.
+fn sorted_suffixes(
+  mut suffixes: Vec<(String, Target)>,
+  source: &'static str,
+  entries: &mut Vec<FileTypeEntry>,
+) -> Vec<(String, Target)> {
+  let mut seen = HashSet::new();
+  suffixes.retain(|(suffix, _)| seen.insert(suffix.clone()));
+  // Stable: earlier rules stay ahead of later ones of the same length.
+  suffixes.sort_by_key(|(suffix, _)| std::cmp::Reverse(suffix.len()));
+  entries.extend(
+    suffixes
+      .iter()
+      .map(|(suffix, target)| entry("suffix", suffix, target, source)),
+  );
+  suffixes
+}
+
+impl FileTypeRegistry {
+  pub fn new(user_rules: &[FileTypeRule]) -> Self {
+    let mut globs = Vec::new();
+    let mut entries = Vec::new();
+    let mut user_suffixes = Vec::new();
+    let mut user_extensions = HashMap::new();
+    let mut extension_entries = Vec::new();
+
+    for rule in user_rules {
+      let target = match rule.category.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(name) => match known_category(name) {
+          Some(category) => Some(FileType {
+            category,
+            language: rule
+              .language
+              .as_deref()
+              .map(str::trim)
+              .filter(|language| !language.is_empty())
+              .map(str::to_lowercase),
+          }),
+          None => {
+            log::warn!("ignoring file type rule with unknown category {:?}", name);
+            continue;
+          }
+        },
+      };
+
+      match (&rule.extension, &rule.suffix, &rule.glob) {
+        (Some(extension), None, None) => {
+          let extension = extension.trim().trim_start_matches('.').to_lowercase();
+          if extension.is_empty() || user_extensions.contains_key(&extension) {
+            continue;
+          }
+          extension_entries.push(entry("extension", &extension, &target, "user"));
+          user_extensions.insert(extension, target);
+        }
+        (None, Some(suffix), None) => {
+          let suffix = suffix.trim().to_lowercase();
+          if !suffix.is_empty() {
+            user_suffixes.push((suffix, target));
+          }
+        }
+        (None, None, Some(pattern)) => {
+          let pattern = pattern.trim();
+          let glob = GlobBuilder::new(pattern)
+            .case_insensitive(true)
+            .literal_separator(true)
+            .build();
+          match glob {
+            Ok(glob) => {
+              entries.push(entry("glob", pattern, &target, "user"));
+              globs.push(GlobRule {
+                matcher: glob.compile_matcher(),
+                on_name: !pattern.contains('/'),
+                target,
+              });
+            }
+            Err(error) => log::warn!("ignoring invalid file type glob {:?}: {}", pattern, error),
+          }
+        }
+        _ => log::warn!("ignoring file type rule without exactly one of extension/suffix/glob"),
+      }
+    }
+
+    let user = SuffixRules {
+      suffixes: sorted_suffixes(user_suffixes, "user", &mut entries),
+      extensions: user_extensions,
+    };
+    entries.append(&mut extension_entries);
+
+    let mut names = HashMap::new();
+    for (name, language) in BUILTIN_CODE_FILE_NAMES {
+      let target = FileType::builtin("code", Some(language));
+      entries.push(entry("name", name, &target, "builtin"));
+      names.insert(name.to_string(), target);
+    }
+
+    // Built-in rules that a user rule of the same pattern replaces are left out.
+    let builtin_suffixes = BUILTIN_SUFFIXES
+      .iter()
+      .filter(|(suffix, _)| !user.suffixes.iter().any(|(user_suffix, _)| user_suffix == suffix))
+      .map(|(suffix, category)| (suffix.to_string(), FileType::builtin(category, None)))
+      .collect();
+    let builtin_suffixes = sorted_suffixes(builtin_suffixes, "builtin", &mut entries);
+
+    let builtin_extensions = BUILTIN_EXTENSIONS
+      .iter()
+      .map(|(extension, category)| (*extension, FileType::builtin(category, None)))
+      .chain(
+        BUILTIN_CODE_EXTENSIONS
+          .iter()
+          .map(|(extension, language)| (*extension, FileType::builtin("code", Some(language)))),
+      );
+    let mut extensions = HashMap::new();
+    for (extension, target) in builtin_extensions {
+      if user.extensions.contains_key(extension) || extensions.contains_key(extension) {
+        continue;
+      }
+      entries.push(entry("extension", extension, &target, "builtin"));
+      extensions.insert(extension.to_string(), target);
+    }
+
+    Self {
+      globs,
+      user,
+      names,
+      builtin: SuffixRules {
+        suffixes: builtin_suffixes,
+        extensions,
+      },
+      entries,
+    }
+  }
+
+  fn lookup(&self, virtual_path: &str) -> Option<&FileType> {
+    let name = virtual_path.rsplit('/').next().filter(|name| !name.is_empty())?;
+
+    for glob in &self.globs {
+      let matched = if glob.on_name {
+        glob.matcher.is_match(name)
+      } else {
+        glob.matcher.is_match(virtual_path)
+      };
+      if matched {
+        return glob.target.as_ref();
+      }
+    }
+
+    let name_lower = name.to_lowercase();
+    if let Some(target) = self.user.lookup(&name_lower) {
+      return target.as_ref();
+    }
+    if let Some(target) = self.names.get(&name_lower) {
+      return target.as_ref();
+    }
+    self.builtin.lookup(&name_lower)?.as_ref()
+  }
+
+  pub fn categorize(&self, virtual_path: &str) -> Option<&'static str> {
+    self.lookup(virtual_path).map(|file_type| file_type.category)
+  }
+
+  /// The language id of the rule that puts `virtual_path` into `category`, if that rule names
+  /// one.
+  pub fn language(&self, virtual_path: &str, category: &str) -> Option<&str> {
+    let file_type = self.lookup(virtual_path)?;
+    if file_type.category != category {
+      return None;
+    }
+    file_type.language.as_deref()
+  }
+
+  pub(crate) fn info(&self) -> FileTypesInfo {
+    FileTypesInfo {
+      categories: CATEGORY_ORDER.to_vec(),
+      rules: self.entries.clone(),
+    }
+  }
+}
+
+/// Maps the program on a `#!` line (`/usr/bin/env python3`, `/bin/sh`) to a language id.
+pub(crate) fn shebang_language(line: &str) -> Option<&'static str> {
+  let mut words = line.strip_prefix("#!")?.split_whitespace();
+  let mut program = words.next()?.rsplit('/').next()?;
+  if program == "env" {
+    program = words.find(|word| !word.starts_with('-'))?;
+  }
+  let program = program
+    .trim_end_matches(|ch: char| ch.is_ascii_digit() || ch == '.')
+    .to_ascii_lowercase();
+  SHEBANG_INTERPRETERS
+    .iter()
+    .find(|(interpreter, _)| *interpreter == program)
+    .map(|(_, language)| *language)
+}
+
+static ACTIVE_REGISTRY: RwLock<Option<Arc<FileTypeRegistry>>> = RwLock::new(None);
+
+/// Replaces the registry used by scans, watchers and `get_file_types`.
+pub(crate) fn install(user_rules: &[FileTypeRule]) {
+  let registry = Arc::new(FileTypeRegistry::new(user_rules));
+  if let Ok(mut active) = ACTIVE_REGISTRY.write() {
+    *active = Some(registry);
+  }
+}
+
+/// The installed registry, or the built-in table when none has been installed yet.
+pub(crate) fn current() -> Arc<FileTypeRegistry> {
+  if let Some(registry) = ACTIVE_REGISTRY.read().ok().and_then(|active| active.clone()) {
+    return registry;
+  }
+  static BUILTIN: OnceLock<Arc<FileTypeRegistry>> = OnceLock::new();
+  BUILTIN
+    .get_or_init(|| Arc::new(FileTypeRegistry::new(&[])))
+    .clone()
+}
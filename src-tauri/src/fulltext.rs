@@ -0,0 +1,761 @@
+use crate::scan::ScanFile;
+use crate::search::{self, ContentMatcher, SearchOptions};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Bumped whenever tokenization or the stored layout changes; older indexes are rebuilt.
+const INDEX_FORMAT_VERSION: u32 = 2;
+const INDEX_FILE_NAME: &str = "index.jsonl";
+/// The file is rewritten once it holds this many lines per live document, most of them replaced
+/// or removed documents, and at least `COMPACT_MIN_LINES` in all.
+const COMPACT_RATIO: usize = 2;
+const COMPACT_MIN_LINES: usize = 256;
+/// Longer runs of letters and digits are hashes or base64 blobs, not words.
+const MAX_TOKEN_CHARS: usize = 64;
+const BM25_K1: f32 = 1.2;
+const BM25_B: f32 = 0.75;
+pub(crate) const DEFAULT_SEARCH_LIMIT: usize = 50;
+const DEFAULT_MAX_INDEXED_FILES: usize = 20_000;
+const DEFAULT_MAX_INDEXED_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Whether scans build full-text indexes, and how much of a root they take in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FullTextOptions {
+  /// Index the searchable files of every scanned folder in the background.
+  pub enabled: bool,
+  /// Index at most this many files per root, the first ones in tree order.
+  pub max_files: usize,
+  /// Stop indexing a root once the files taken in add up to this many bytes on disk.
+  pub max_bytes: u64,
+}
+
+impl Default for FullTextOptions {
+  fn default() -> Self {
+    Self {
+      enabled: true,
+      max_files: DEFAULT_MAX_INDEXED_FILES,
+      max_bytes: DEFAULT_MAX_INDEXED_BYTES,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexedDoc {
+  virtual_path: String,
+  abs_path: String,
+  category: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  modified_ms: Option<u64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  size: Option<u64>,
+  /// Token count, used for BM25 length normalisation.
+  length: u32,
+}
+
+/// One line of the index file. The first is the header and the others replay in order, so an
+/// update only appends the documents it added and removed.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum LogLine<'a> {
+  Header {
+    version: u32,
+    root: &'a str,
+  },
+  Add {
+    id: u32,
+    doc: &'a IndexedDoc,
+    terms: &'a [(String, u32)],
+  },
+  Remove {
+    id: u32,
+  },
+}
+
+/// [`LogLine`] as read back.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum StoredLine {
+  Header {
+    version: u32,
+    root: String,
+  },
+  Add {
+    id: u32,
+    doc: IndexedDoc,
+    terms: Vec<(String, u32)>,
+  },
+  Remove {
+    id: u32,
+  },
+}
+
+/// A change not yet written to the index file.
+enum Change {
+  Add(u32),
+  Remove(u32),
+}
+
+/// One ranked document.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct IndexHit {
+  virtual_path: String,
+  abs_path: String,
+  category: String,
+  score: f32,
+  /// The first line that contains one of the query words, as in `search_content`.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  line_number: Option<u64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  snippet: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  match_start: Option<usize>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  match_end: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexSearchResult {
+  hits: Vec<IndexHit>,
+  /// Documents that matched every query term, including those past `limit`.
+  total: usize,
+  indexed_files: usize,
+  /// An update is still running, so recent changes may be missing from the results.
+  indexing: bool,
+  /// The last update stopped at `maxFiles` or `maxBytes`; later files are not indexed.
+  truncated: bool,
+}
+
+struct FullTextIndex {
+  file: Option<PathBuf>,
+  root: String,
+  loaded: bool,
+  /// Indexed by document id; removed documents leave a hole that a later document reuses.
+  docs: Vec<Option<IndexedDoc>>,
+  /// The terms of each document with their frequency, so removing it only touches its own
+  /// posting lists.
+  doc_terms: Vec<Vec<(String, u32)>>,
+  /// Term to flat `[doc, term frequency, doc, term frequency, ...]` pairs.
+  postings: HashMap<String, Vec<u32>>,
+  by_path: HashMap<String, u32>,
+  free: Vec<u32>,
+  pending: Vec<Change>,
+  /// Lines in the index file.
+  logged: usize,
+  /// The file is missing, outdated or broken past some line, so the next save writes it whole.
+  rewrite: bool,
+}
+
+impl FullTextIndex {
+  fn new(root: &Path, file: Option<PathBuf>) -> Self {
+    Self {
+      file,
+      root: root.to_string_lossy().into_owned(),
+      loaded: false,
+      docs: Vec::new(),
+      doc_terms: Vec::new(),
+      postings: HashMap::new(),
+      by_path: HashMap::new(),
+      free: Vec::new(),
+      pending: Vec::new(),
+      logged: 0,
+      rewrite: true,
+    }
+  }
+
+  /// Reads the saved index the first time it is needed. A missing, unreadable or outdated file
+  /// leaves the index empty, so the next refresh rebuilds it; a broken line keeps the documents
+  /// before it.
+  fn ensure_loaded(&mut self) {
+    if self.loaded {
+      return;
+    }
+    self.loaded = true;
+    let Some(file) = &self.file else {
+      return;
+    };
+    let Ok(content) = std::fs::read_to_string(file) else {
+      return;
+    };
+    let mut lines = content.lines();
+    match lines.next().map(serde_json::from_str::<StoredLine>) {
+      Some(Ok(StoredLine::Header { version, root }))
+        if version == INDEX_FORMAT_VERSION && root == self.root => {}
+      _ => return,
+    }
+    let file = file.clone();
+    self.logged = 1;
+    self.rewrite = false;
+    for line in lines {
+      let error = match serde_json::from_str::<StoredLine>(line) {
+        Ok(StoredLine::Add { id, doc, terms }) if id as usize <= self.docs.len() => {
+          self.insert(id, doc, terms);
+          None
+        }
+        Ok(StoredLine::Remove { id }) => {
+          self.remove(id);
+          None
+        }
+        Ok(_) => Some("无效的索引记录".to_string()),
+        Err(error) => Some(error.to_string()),
+      };
+      if let Some(error) = error {
+        log::warn!("全文索引已损坏，将重新写入 ({}): {}", file.display(), error);
+        self.rewrite = true;
+        break;
+      }
+      self.logged += 1;
+    }
+    self.free = (0..self.docs.len() as u32)
+      .filter(|id| self.docs[*id as usize].is_none())
+      .collect();
+  }
+
+  /// Appends the changes since the last save, or rewrites the file when it has to be or when
+  /// replaced documents make up most of it. Does nothing when nothing changed.
+  fn save(&mut self) -> Result<(), String> {
+    if self.pending.is_empty() && !self.rewrite {
+      return Ok(());
+    }
+    let Some(file) = self.file.clone() else {
+      self.pending.clear();
+      return Ok(());
+    };
+    let lines = self.logged + self.pending.len();
+    let compact = lines > COMPACT_MIN_LINES.max(COMPACT_RATIO * self.by_path.len());
+    let result = if self.rewrite || compact {
+      self.write_all(&file)
+    } else {
+      self.append(&file)
+    };
+    self.pending.clear();
+    // After a failed write the file may end in a partial line, and the changes were dropped.
+    self.rewrite = result.is_err();
+    result
+  }
+
+  fn append(&mut self, file: &Path) -> Result<(), String> {
+    let mut content = Vec::new();
+    for change in &self.pending {
+      let line = match *change {
+        Change::Add(id) => self.add_line(id),
+        Change::Remove(id) => Some(LogLine::Remove { id }),
+      };
+      if let Some(line) = line {
+        push_line(&mut content, &line)?;
+      }
+    }
+    std::fs::OpenOptions::new()
+      .append(true)
+      .open(file)
+      .and_then(|mut opened| opened.write_all(&content))
+      .map_err(|error| format!("写入索引失败 ({}): {}", file.display(), error))?;
+    self.logged += self.pending.len();
+    Ok(())
+  }
+
+  fn write_all(&mut self, file: &Path) -> Result<(), String> {
+    if let Some(parent) = file.parent() {
+      std::fs::create_dir_all(parent)
+        .map_err(|error| format!("创建索引目录失败 ({}): {}", parent.display(), error))?;
+    }
+    let mut content = Vec::new();
+    let header = LogLine::Header {
+      version: INDEX_FORMAT_VERSION,
+      root: &self.root,
+    };
+    push_line(&mut content, &header)?;
+    for id in 0..self.docs.len() as u32 {
+      if let Some(line) = self.add_line(id) {
+        push_line(&mut content, &line)?;
+      }
+    }
+
+    let tmp_path = file.with_extension("tmp");
+    std::fs::write(&tmp_path, content)
+      .map_err(|error| format!("写入索引失败 ({}): {}", tmp_path.display(), error))?;
+    if std::fs::rename(&tmp_path, file).is_err() {
+      let _ = std::fs::remove_file(file);
+      std::fs::rename(&tmp_path, file)
+        .map_err(|error| format!("替换索引失败 ({}): {}", file.display(), error))?;
+    }
+    self.logged = 1 + self.by_path.len();
+    Ok(())
+  }
+
+  fn add_line(&self, id: u32) -> Option<LogLine<'_>> {
+    Some(LogLine::Add {
+      id,
+      doc: self.docs[id as usize].as_ref()?,
+      terms: &self.doc_terms[id as usize],
+    })
+  }
+
+  fn indexed_files(&self) -> usize {
+    self.by_path.len()
+  }
+
+  /// Splits `files` into those that are new or changed since they were indexed, and the ids of
+  /// documents to drop: changed ones, and with a `complete` scan also those no longer present.
+  fn plan(
+    &self,
+    files: Vec<ScanFile>,
+    complete: bool,
+    options: &FullTextOptions,
+  ) -> (Vec<ScanFile>, HashSet<u32>, bool) {
+    let mut stale = Vec::new();
+    let mut removed = HashSet::new();
+    let mut seen = HashSet::new();
+    let mut bytes = 0u64;
+    let mut truncated = false;
+    for file in files {
+      if !search::is_searchable_category(&file.category) {
+        continue;
+      }
+      bytes = bytes.saturating_add(file.size.unwrap_or(0));
+      if seen.len() >= options.max_files || bytes > options.max_bytes {
+        // Files past the limit count as gone, so a complete scan drops them from the index.
+        truncated = true;
+        break;
+      }
+      seen.insert(file.virtual_path.clone());
+      let current = self.by_path.get(&file.virtual_path).and_then(|id| {
+        let doc = self.docs[*id as usize].as_ref()?;
+        Some((*id, doc))
+      });
+      match current {
+        Some((_, doc))
+          if doc.modified_ms == file.modified_ms
+            && doc.size == file.size
+            && doc.abs_path == file.abs_path => {}
+        Some((id, _)) => {
+          removed.insert(id);
+          stale.push(file);
+        }
+        None => stale.push(file),
+      }
+    }
+    if complete {
+      for (path, id) in &self.by_path {
+        if !seen.contains(path) {
+          removed.insert(*id);
+        }
+      }
+    }
+    (stale, removed, truncated)
+  }
+
+  fn apply(&mut self, removed: &HashSet<u32>, added: Vec<(IndexedDoc, HashMap<String, u32>)>) {
+    for id in removed {
+      if self.docs[*id as usize].is_some() {
+        self.remove(*id);
+        self.free.push(*id);
+        self.pending.push(Change::Remove(*id));
+      }
+    }
+    for (doc, terms) in added {
+      let id = self.free.pop().unwrap_or(self.docs.len() as u32);
+      self.insert(id, doc, terms.into_iter().collect());
+      self.pending.push(Change::Add(id));
+    }
+  }
+
+  /// Puts `doc` at `id`, which is at most one past the last document.
+  fn insert(&mut self, id: u32, doc: IndexedDoc, terms: Vec<(String, u32)>) {
+    if id as usize == self.docs.len() {
+      self.docs.push(None);
+      self.doc_terms.push(Vec::new());
+    } else {
+      self.remove(id);
+    }
+    for (term, frequency) in &terms {
+      self.postings.entry(term.clone()).or_default().extend([id, *frequency]);
+    }
+    self.by_path.insert(doc.virtual_path.clone(), id);
+    self.docs[id as usize] = Some(doc);
+    self.doc_terms[id as usize] = terms;
+  }
+
+  fn remove(&mut self, id: u32) {
+    let Some(doc) = self.docs.get_mut(id as usize).and_then(Option::take) else {
+      return;
+    };
+    self.by_path.remove(&doc.virtual_path);
+    for (term, _) in std::mem::take(&mut self.doc_terms[id as usize]) {
+      let Some(pairs) = self.postings.get_mut(&term) else {
+        continue;
+      };
+      if let Some(index) = pairs.chunks_exact(2).position(|pair| pair[0] == id) {
+        // Posting order does not matter, so the last pair fills the hole.
+        let last = pairs.len() - 2;
+        pairs.copy_within(last.., index * 2);
+        pairs.truncate(last);
+      }
+      if pairs.is_empty() {
+        self.postings.remove(&term);
+      }
+    }
+  }
+
+  /// Documents containing every query term, best BM25 score first, and how many there were.
+  fn rank(&self, query: &str, limit: usize) -> (Vec<(IndexedDoc, f32)>, usize) {
+    let mut terms = Vec::new();
+    tokenize(query, false, |term| {
+      if !terms.contains(&term) {
+        terms.push(term);
+      }
+    });
+    if terms.is_empty() {
+      return (Vec::new(), 0);
+    }
+
+    let docs = &self.docs;
+    let doc_count = self.by_path.len().max(1) as f32;
+    let total_length: u64 = docs.iter().flatten().map(|doc| u64::from(doc.length)).sum();
+    let average_length = (total_length as f32 / doc_count).max(1.0);
+
+    let mut scores: HashMap<u32, (f32, usize)> = HashMap::new();
+    for term in &terms {
+      let Some(pairs) = self.postings.get(term) else {
+        return (Vec::new(), 0);
+      };
+      let doc_frequency = (pairs.len() / 2) as f32;
+      let idf = (1.0 + (doc_count - doc_frequency + 0.5) / (doc_frequency + 0.5)).ln();
+      for pair in pairs.chunks_exact(2) {
+        let Some(doc) = &docs[pair[0] as usize] else {
+          continue;
+        };
+        let frequency = pair[1] as f32;
+        let norm = 1.0 - BM25_B + BM25_B * doc.length as f32 / average_length;
+        let score = idf * frequency * (BM25_K1 + 1.0) / (frequency + BM25_K1 * norm);
+        let entry = scores.entry(pair[0]).or_default();
+        entry.0 += score;
+        entry.1 += 1;
+      }
+    }
+
+    let mut ranked: Vec<(&IndexedDoc, f32)> = scores
+      .into_iter()
+      .filter(|(_, (_, matched))| *matched == terms.len())
+      .filter_map(|(id, (score, _))| Some((docs[id as usize].as_ref()?, score)))
+      .collect();
+    ranked.sort_by(|a, b| {
+      b.1.total_cmp(&a.1).then_with(|| a.0.virtual_path.cmp(&b.0.virtual_path))
+    });
+    let total = ranked.len();
+    let hits = ranked
+      .into_iter()
+      .take(limit)
+      .map(|(doc, score)| (doc.clone(), score))
+      .collect();
+    (hits, total)
+  }
+}
+
+/// The index of one scanned root.
+struct RootIndex {
+  index: Mutex<FullTextIndex>,
+  /// Held for a whole refresh so two scans of the same root never interleave their updates.
+  refresh: Mutex<()>,
+  indexing: AtomicBool,
+  truncated: AtomicBool,
+}
+
+/// Full-text indexes of the roots scanned in this session, persisted below `base_dir`.
+pub struct FullTextState {
+  base_dir: Option<PathBuf>,
+  options: RwLock<FullTextOptions>,
+  roots: Mutex<HashMap<PathBuf, Arc<RootIndex>>>,
+}
+
+impl FullTextState {
+  /// Without a `base_dir` the indexes only live in memory.
+  pub fn new(base_dir: Option<PathBuf>) -> Self {
+    Self {
+      base_dir,
+      options: RwLock::new(FullTextOptions::default()),
+      roots: Mutex::new(HashMap::new()),
+    }
+  }
+
+  /// Applies to the updates that start from now on.
+  pub fn set_options(&self, options: FullTextOptions) {
+    if let Ok(mut current) = self.options.write() {
+      *current = options;
+    }
+  }
+
+  fn options(&self) -> FullTextOptions {
+    match self.options.read() {
+      Ok(options) => options.clone(),
+      Err(poisoned) => poisoned.into_inner().clone(),
+    }
+  }
+
+  fn root_index(&self, root: &Path) -> Result<Arc<RootIndex>, String> {
+    let Ok(mut roots) = self.roots.lock() else {
+      return Err("全文索引状态不可用".to_string());
+    };
+    let root_index = roots.entry(root.to_path_buf()).or_insert_with(|| {
+      let file = self
+        .base_dir
+        .as_ref()
+        .map(|base_dir| base_dir.join(root_hash(root)).join(INDEX_FILE_NAME));
+      Arc::new(RootIndex {
+        index: Mutex::new(FullTextIndex::new(root, file)),
+        refresh: Mutex::new(()),
+        indexing: AtomicBool::new(false),
+        truncated: AtomicBool::new(false),
+      })
+    });
+    Ok(root_index.clone())
+  }
+
+  /// Brings the index of `root` up to date with the files a scan just listed, on a background
+  /// thread. Only new files and files whose size or mtime changed are read again; `complete`
+  /// says the scan was not truncated, so files it did not list are gone. Does nothing while
+  /// indexing is turned off.
+  pub(crate) fn refresh(
+    &self,
+    root: &Path,
+    files: Vec<ScanFile>,
+    complete: bool,
+  ) -> Result<(), String> {
+    let options = self.options();
+    if !options.enabled {
+      return Ok(());
+    }
+    let root_index = self.root_index(root)?;
+    std::thread::Builder::new()
+      .name("rustreader-index".to_string())
+      .spawn(move || {
+        if let Err(error) = refresh_root(&root_index, files, complete, &options) {
+          log::warn!("{}", error);
+        }
+      })
+      .map(|_| ())
+      .map_err(|error| format!("启动索引线程失败: {}", error))
+  }
+
+  /// [`FullTextState::refresh`] on the calling thread.
+  pub fn update(&self, root: &Path, files: Vec<ScanFile>, complete: bool) -> Result<(), String> {
+    let options = self.options();
+    if !options.enabled {
+      return Ok(());
+    }
+    let root_index = self.root_index(root)?;
+    refresh_root(&root_index, files, complete, &options)
+  }
+
+  pub fn search(
+    &self,
+    root: &Path,
+    query: &str,
+    limit: usize,
+  ) -> Result<IndexSearchResult, String> {
+    if !self.options().enabled {
+      return Err("全文索引已关闭".to_string());
+    }
+    let root_index = self.root_index(root)?;
+    let (ranked, total, indexed_files) = {
+      let Ok(mut index) = root_index.index.lock() else {
+        return Err("全文索引不可用".to_string());
+      };
+      index.ensure_loaded();
+      let (ranked, total) = index.rank(query, limit);
+      (ranked, total, index.indexed_files())
+    };
+
+    let matcher = snippet_matcher(query);
+    let hits = ranked
+      .into_iter()
+      .map(|(doc, score)| {
+        let found = matcher.as_ref().and_then(|matcher| {
+          let file = ScanFile::new(doc.virtual_path.clone(), doc.abs_path.clone(), &doc.category);
+          matcher.search_file(&file, 1).ok().flatten()?.into_iter().next()
+        });
+        IndexHit {
+          score,
+          line_number: found.as_ref().map(|found| found.line_number),
+          match_start: found.as_ref().map(|found| found.match_start),
+          match_end: found.as_ref().map(|found| found.match_end),
+          snippet: found.map(|found| found.snippet),
+          virtual_path: doc.virtual_path,
+          abs_path: doc.abs_path,
+          category: doc.category,
+        }
+      })
+      .collect();
+
+    Ok(IndexSearchResult {
+      hits,
+      total,
+      indexed_files,
+      indexing: root_index.indexing.load(Ordering::Relaxed),
+      truncated: root_index.truncated.load(Ordering::Relaxed),
+    })
+  }
+}
+
+fn refresh_root(
+  root_index: &RootIndex,
+  files: Vec<ScanFile>,
+  complete: bool,
+  options: &FullTextOptions,
+) -> Result<(), String> {
+  let Ok(_refreshing) = root_index.refresh.lock() else {
+    return Err("全文索引不可用".to_string());
+  };
+  root_index.indexing.store(true, Ordering::Relaxed);
+  let result = update_root(root_index, files, complete, options);
+  root_index.indexing.store(false, Ordering::Relaxed);
+  result
+}
+
+fn update_root(
+  root_index: &RootIndex,
+  mut files: Vec<ScanFile>,
+  complete: bool,
+  options: &FullTextOptions,
+) -> Result<(), String> {
+  // Scans without `collectMetadata` leave the change markers empty.
+  files.par_iter_mut().for_each(|file| {
+    if file.modified_ms.is_none() || file.size.is_none() {
+      if let Ok(metadata) = std::fs::metadata(&file.abs_path) {
+        file.apply_metadata(&metadata);
+      }
+    }
+  });
+
+  let (stale, removed) = {
+    let Ok(mut index) = root_index.index.lock() else {
+      return Err("全文索引不可用".to_string());
+    };
+    index.ensure_loaded();
+    let (stale, removed, truncated) = index.plan(files, complete, options);
+    root_index.truncated.store(truncated, Ordering::Relaxed);
+    (stale, removed)
+  };
+  if stale.is_empty() && removed.is_empty() {
+    return Ok(());
+  }
+
+  // Reading and tokenizing is the slow part and runs without holding the index lock.
+  let added: Vec<_> = stale.into_par_iter().filter_map(index_document).collect();
+  let Ok(mut index) = root_index.index.lock() else {
+    return Err("全文索引不可用".to_string());
+  };
+  index.apply(&removed, added);
+  index.save()
+}
+
+fn push_line(content: &mut Vec<u8>, line: &LogLine) -> Result<(), String> {
+  serde_json::to_writer(&mut *content, line)
+    .map_err(|error| format!("序列化索引失败: {}", error))?;
+  content.push(b'\n');
+  Ok(())
+}
+
+fn index_document(file: ScanFile) -> Option<(IndexedDoc, HashMap<String, u32>)> {
+  let text = search::searchable_text(Path::new(&file.abs_path), &file.category).ok()??;
+  let mut terms: HashMap<String, u32> = HashMap::new();
+  let mut length = 0u32;
+  tokenize(&text, true, |term| {
+    *terms.entry(term).or_default() += 1;
+    length = length.saturating_add(1);
+  });
+  let doc = IndexedDoc {
+    virtual_path: file.virtual_path,
+    abs_path: file.abs_path,
+    category: file.category,
+    modified_ms: file.modified_ms,
+    size: file.size,
+    length,
+  };
+  Some((doc, terms))
+}
+
+/// Finds the snippet line: any of the whitespace-separated query words, ignoring case.
+fn snippet_matcher(query: &str) -> Option<ContentMatcher> {
+  let words: Vec<String> = query.split_whitespace().map(regex::escape).collect();
+  if words.is_empty() {
+    return None;
+  }
+  let options = SearchOptions {
+    regex: true,
+    ..SearchOptions::default()
+  };
+  ContentMatcher::new(&words.join("|"), &options).ok()
+}
+
+/// Splits `text` into lowercase words, and runs of CJK characters into overlapping bigrams,
+/// since those scripts do not separate words with spaces. `with_unigrams` also emits every CJK
+/// character on its own, which the index needs so single-character queries still match; a
+/// lone CJK character always becomes a unigram.
+pub fn tokenize(text: &str, with_unigrams: bool, mut emit: impl FnMut(String)) {
+  let mut word = String::new();
+  let mut run: Vec<char> = Vec::new();
+  for ch in text.chars().chain(std::iter::once(' ')) {
+    if is_cjk(ch) {
+      flush_word(&mut word, &mut emit);
+      run.push(ch);
+      continue;
+    }
+    flush_cjk_run(&mut run, with_unigrams, &mut emit);
+    if ch.is_alphanumeric() {
+      word.extend(ch.to_lowercase());
+    } else {
+      flush_word(&mut word, &mut emit);
+    }
+  }
+}
+
+fn flush_word(word: &mut String, emit: &mut impl FnMut(String)) {
+  if !word.is_empty() && word.chars().count() <= MAX_TOKEN_CHARS {
+    emit(std::mem::take(word));
+  }
+  word.clear();
+}
+
+fn flush_cjk_run(run: &mut Vec<char>, with_unigrams: bool, emit: &mut impl FnMut(String)) {
+  if run.len() == 1 || with_unigrams {
+    for ch in run.iter() {
+      emit(ch.to_string());
+    }
+  }
+  for pair in run.windows(2) {
+    emit(pair.iter().collect());
+  }
+  run.clear();
+}
+
+fn is_cjk(ch: char) -> bool {
+  matches!(
+    ch,
+    '\u{3040}'..='\u{30ff}'
+      | '\u{3400}'..='\u{4dbf}'
+      | '\u{4e00}'..='\u{9fff}'
+      | '\u{ac00}'..='\u{d7af}'
+      | '\u{f900}'..='\u{faff}'
+      | '\u{20000}'..='\u{2ffff}'
+  )
+}
+
+/// Directory name of the index of `root`: 64-bit FNV-1a of the path, stable across builds
+/// unlike `DefaultHasher`.
+fn root_hash(root: &Path) -> String {
+  let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+  for byte in root.to_string_lossy().as_bytes() {
+    hash ^= u64::from(*byte);
+    hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+  }
+  format!("{:016x}", hash)
+}
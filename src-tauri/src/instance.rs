@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Manager};
+
+pub(crate) const OPEN_REQUEST_EVENT: &str = "rustreader_open_request";
+/// How long a second instance waits for the running one to acknowledge its request.
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(3);
+/// How often a claim that lost the race to another invocation tries to reach the winner.
+const CLAIM_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+const ACK: &str = "ok";
+
+/// What a later invocation asks the running instance to do.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenRequest {
+  /// Absolute paths, resolved against the working directory of the invocation that sent them.
+  /// Several are opened as one workspace.
+  #[serde(default)]
+  pub(crate) open_targets: Vec<String>,
+  /// The document to preselect: relative to the opened folder, or an absolute path.
+  pub(crate) select: Option<String>,
+  /// The heading to scroll to once the selected document is shown.
+  pub(crate) anchor: Option<String>,
+  pub(crate) site_name: Option<String>,
+}
+
+#[cfg(unix)]
+type Listener = std::os::unix::net::UnixListener;
+#[cfg(unix)]
+type Stream = std::os::unix::net::UnixStream;
+#[cfg(not(unix))]
+type Listener = std::net::TcpListener;
+#[cfg(not(unix))]
+type Stream = std::net::TcpStream;
+
+/// The outcome of [`claim`].
+pub enum Instance {
+  /// No other instance answered; this one now owns the lock and should serve it.
+  Primary(Lock),
+  /// The running instance accepted the request, so this process can exit.
+  Forwarded,
+}
+
+/// The lock of the primary instance, held until it is dropped.
+pub struct Lock {
+  listener: Listener,
+  /// Locked for the life of the instance, so no later invocation replaces `listener` while it
+  /// runs; the system releases it when the process exits, however it exits.
+  _guard: File,
+}
+
+/// Hands `request` to the instance holding the lock at `lock_path`, or takes the lock when none
+/// is running. A lock left behind by a crashed instance is replaced. When another invocation
+/// takes the lock at the same moment, this one forwards to it instead, or gives up once that
+/// instance has not answered for [`FORWARD_TIMEOUT`].
+pub fn claim(lock_path: &Path, request: &OpenRequest) -> Result<Instance, String> {
+  if let Some(parent) = lock_path.parent() {
+    std::fs::create_dir_all(parent)
+      .map_err(|error| format!("创建配置目录失败 ({}): {}", parent.display(), error))?;
+  }
+  let guard_path = lock_path.with_extension("lock");
+  let deadline = Instant::now() + FORWARD_TIMEOUT;
+  loop {
+    if let Some(stream) = connect(lock_path) {
+      if forward(stream, request).is_ok() {
+        return Ok(Instance::Forwarded);
+      }
+    }
+    if let Some(guard) = try_lock(&guard_path)? {
+      let listener = bind(lock_path)?;
+      return Ok(Instance::Primary(Lock {
+        listener,
+        _guard: guard,
+      }));
+    }
+    if Instant::now() >= deadline {
+      return Err(format!("另一个实例没有响应 ({})", lock_path.display()));
+    }
+    std::thread::sleep(CLAIM_RETRY_INTERVAL);
+  }
+}
+
+/// The guard file at `guard_path`, exclusively locked, or `None` while another process holds it.
+#[cfg(unix)]
+fn try_lock(guard_path: &Path) -> Result<Option<File>, String> {
+  use std::os::unix::io::AsRawFd;
+  let guard = OpenOptions::new()
+    .create(true)
+    .truncate(false)
+    .write(true)
+    .open(guard_path)
+    .map_err(|error| format!("打开单实例锁失败 ({}): {}", guard_path.display(), error))?;
+  // SAFETY: `flock` only reads the descriptor, which `guard` keeps open for the call.
+  let locked = unsafe { libc::flock(guard.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0;
+  Ok(locked.then_some(guard))
+}
+
+/// Opening the guard without sharing fails while another process has it open.
+#[cfg(not(unix))]
+fn try_lock(guard_path: &Path) -> Result<Option<File>, String> {
+  use std::os::windows::fs::OpenOptionsExt;
+  const ERROR_SHARING_VIOLATION: i32 = 32;
+  let opened = OpenOptions::new()
+    .create(true)
+    .truncate(false)
+    .write(true)
+    .share_mode(0)
+    .open(guard_path);
+  match opened {
+    Ok(guard) => Ok(Some(guard)),
+    Err(error) if error.raw_os_error() == Some(ERROR_SHARING_VIOLATION) => Ok(None),
+    Err(error) => Err(format!("打开单实例锁失败 ({}): {}", guard_path.display(), error)),
+  }
+}
+
+fn forward(mut stream: Stream, request: &OpenRequest) -> std::io::Result<()> {
+  stream.set_read_timeout(Some(FORWARD_TIMEOUT))?;
+  stream.set_write_timeout(Some(FORWARD_TIMEOUT))?;
+  let mut line = serde_json::to_string(request)?;
+  line.push('\n');
+  stream.write_all(line.as_bytes())?;
+  stream.flush()?;
+
+  let mut reply = String::new();
+  BufReader::new(stream).read_line(&mut reply)?;
+  if reply.trim() == ACK {
+    Ok(())
+  } else {
+    Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "unexpected reply"))
+  }
+}
+
+/// Accepts requests from later invocations on a background thread for the life of the app,
+/// passing each to one window as an [`OPEN_REQUEST_EVENT`].
+pub(crate) fn serve(app: &tauri::AppHandle, lock: Lock) -> Result<(), String> {
+  let app = app.clone();
+  std::thread::Builder::new()
+    .name("rustreader-instance".to_string())
+    .spawn(move || {
+      for stream in lock.listener.incoming() {
+        match stream {
+          Ok(stream) => handle_connection(&app, stream),
+          Err(error) => log::warn!("single-instance accept failed: {}", error),
+        }
+      }
+    })
+    .map(|_| ())
+    .map_err(|error| format!("启动单实例监听线程失败: {}", error))
+}
+
+fn handle_connection(app: &tauri::AppHandle, stream: Stream) {
+  let _ = stream.set_read_timeout(Some(FORWARD_TIMEOUT));
+  let mut reader = BufReader::new(&stream);
+  let mut line = String::new();
+  if reader.read_line(&mut line).is_err() {
+    return;
+  }
+  let Ok(request) = serde_json::from_str::<OpenRequest>(&line) else {
+    return;
+  };
+  let _ = (&stream).write_all(format!("{}\n", ACK).as_bytes());
+
+  // The main window opens the request; when it has been closed, any remaining window does.
+  let window = app
+    .get_webview_window(crate::MAIN_WINDOW_LABEL)
+    .or_else(|| app.webview_windows().into_values().next());
+  let Some(window) = window else {
+    return;
+  };
+  let _ = window.unminimize();
+  let _ = window.show();
+  let _ = window.set_focus();
+  let _ = app.emit_to(window.label(), OPEN_REQUEST_EVENT, request);
+}
+
+#[cfg(unix)]
+fn connect(lock_path: &Path) -> Option<Stream> {
+  Stream::connect(lock_path).ok()
+}
+
+#[cfg(unix)]
+fn bind(lock_path: &Path) -> Result<Listener, String> {
+  // Nothing answered on the socket and its owner released the guard, so any file still there
+  // is stale.
+  let _ = std::fs::remove_file(lock_path);
+  Listener::bind(lock_path)
+    .map_err(|error| format!("创建单实例锁失败 ({}): {}", lock_path.display(), error))
+}
+
+/// Without Unix sockets the lock file records the loopback port the running instance listens on.
+#[cfg(not(unix))]
+fn connect(lock_path: &Path) -> Option<Stream> {
+  let port: u16 = std::fs::read_to_string(lock_path).ok()?.trim().parse().ok()?;
+  let address = std::net::SocketAddr::from(([127, 0, 0, 1], port));
+  Stream::connect_timeout(&address, FORWARD_TIMEOUT).ok()
+}
+
+#[cfg(not(unix))]
+fn bind(lock_path: &Path) -> Result<Listener, String> {
+  let listener = Listener::bind(("127.0.0.1", 0))
+    .map_err(|error| format!("创建单实例锁失败: {}", error))?;
+  let port = listener
+    .local_addr()
+    .map_err(|error| format!("创建单实例锁失败: {}", error))?
+    .port();
+  std::fs::write(lock_path, port.to_string())
+    .map_err(|error| format!("写入单实例锁失败 ({}): {}", lock_path.display(), error))?;
+  Ok(listener)
+}
@@ -1,15 +1,54 @@
+pub mod cli;
+pub mod extract;
+pub mod file_types;
+pub mod fulltext;
+pub mod instance;
+mod ooxml;
+mod pdf;
+pub mod scan;
+pub mod search;
+pub mod site_export;
+pub mod site_index;
+pub mod sniff;
+mod watch;
+pub mod workspace;
+
+use cli::{Command, ExportArgs, IndexArgs, OpenArgs};
+use file_types::{FileTypeRule, FileTypesInfo};
+use fulltext::{FullTextOptions, FullTextState, IndexSearchResult};
+use instance::{Instance, OpenRequest};
+use scan::{
+  categorize_file, ScanCounts, ScanFile, ScanOptions, ScanOutcome, ScanSink, ScanTruncation,
+  ScanWarning,
+};
+use search::{ContentMatcher, SearchMatch, SearchOptions};
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
-use std::ffi::OsString;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tauri::Emitter;
 use tauri::Manager;
+use watch::{FileWatchState, FolderWatchState};
+use workspace::{PrefixedSink, WorkspaceRoot};
 
 const SCAN_PROGRESS_EVENT: &str = "rustreader_scan_progress";
+const SCAN_BATCH_EVENT: &str = "rustreader_scan_batch";
+const SCAN_FINISHED_EVENT: &str = "rustreader_scan_finished";
+const SCAN_BATCH_SIZE: usize = 512;
+const SCAN_BATCH_INTERVAL: Duration = Duration::from_millis(120);
+const SEARCH_MATCHES_EVENT: &str = "rustreader_search_matches";
+const SEARCH_FINISHED_EVENT: &str = "rustreader_search_finished";
+const SEARCH_BATCH_SIZE: usize = 200;
 const APP_PREFIX: &str = "rustreader";
 const APP_TITLE_PREFIX: &str = "rustreader - ";
 const RECENT_LIMIT_DEFAULT: usize = 20;
+/// The window declared in `tauri.conf.json`; the command line and forwarded requests open here.
+pub(crate) const MAIN_WINDOW_LABEL: &str = "main";
+/// Windows opened with `open_in_new_window` are labelled `window-1`, `window-2`, and so on.
+const NEW_WINDOW_LABEL_PREFIX: &str = "window-";
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,6 +57,12 @@ struct AppConfig {
   language: Option<String>,
   #[serde(skip_serializing_if = "Option::is_none")]
   font_size_px: Option<u32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  scan_options: Option<ScanOptions>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  file_types: Option<Vec<FileTypeRule>>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  full_text: Option<FullTextOptions>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -28,15 +73,149 @@ struct ScanProgressEvent {
   scanned_dirs: u64,
   scanned_files: u64,
   matched_files: u64,
+  skipped: u64,
   current_path: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanBatchEvent {
+  scan_id: String,
+  files: Vec<ScanFile>,
+}
+
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct ScanFile {
-  virtual_path: String,
-  abs_path: String,
-  category: String,
+struct ScanFinishedEvent {
+  scan_id: String,
+  stage: &'static str,
+  root: String,
+  label: String,
+  scanned_dirs: u64,
+  scanned_files: u64,
+  matched_files: u64,
+  skipped: u64,
+  truncated: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  truncated_reason: Option<ScanTruncation>,
+  warnings: Vec<ScanWarning>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchMatchesEvent {
+  search_id: String,
+  matches: Vec<SearchMatch>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchFinishedEvent {
+  search_id: String,
+  /// `done` or `cancelled`.
+  stage: &'static str,
+  root: String,
+  searched_files: u64,
+  matched_files: u64,
+  match_count: u64,
+  /// Binary, oversized or unreadable files that were not searched.
+  skipped: u64,
+  /// The search stopped at `maxMatches`.
+  truncated: bool,
+}
+
+/// Cancel flags of running scans and content searches, keyed by the id the frontend chose.
+#[derive(Default)]
+struct ScanRegistry {
+  running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl ScanRegistry {
+  fn register(&self, scan_id: &str) -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    if let Ok(mut running) = self.running.lock() {
+      running.insert(scan_id.to_string(), flag.clone());
+    }
+    flag
+  }
+
+  fn unregister(&self, scan_id: &str) {
+    if let Ok(mut running) = self.running.lock() {
+      running.remove(scan_id);
+    }
+  }
+
+  fn cancel(&self, scan_id: &str) -> bool {
+    let Ok(running) = self.running.lock() else {
+      return false;
+    };
+    match running.get(scan_id) {
+      Some(flag) => {
+        flag.store(true, Ordering::Relaxed);
+        true
+      }
+      None => false,
+    }
+  }
+}
+
+/// What each window should show, keyed by window label: the command line's request for the
+/// main window, the arguments of `open_in_new_window` for the others.
+#[derive(Default)]
+struct WindowTargets {
+  next_id: AtomicU64,
+  targets: Mutex<HashMap<String, OpenRequest>>,
+}
+
+impl WindowTargets {
+  fn next_label(&self) -> String {
+    let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+    format!("{NEW_WINDOW_LABEL_PREFIX}{id}")
+  }
+
+  fn insert(&self, label: &str, request: OpenRequest) {
+    if let Ok(mut targets) = self.targets.lock() {
+      targets.insert(label.to_string(), request);
+    }
+  }
+
+  fn get(&self, label: &str) -> OpenRequest {
+    let Ok(targets) = self.targets.lock() else {
+      return OpenRequest::default();
+    };
+    targets.get(label).cloned().unwrap_or_default()
+  }
+
+  fn remove(&self, label: &str) {
+    if let Ok(mut targets) = self.targets.lock() {
+      targets.remove(label);
+    }
+  }
+}
+
+/// The options each window's tree was last scanned with, keyed by window label, so a content
+/// search walks the same files the tree lists.
+#[derive(Default)]
+struct TreeScanOptions {
+  options: Mutex<HashMap<String, ScanOptions>>,
+}
+
+impl TreeScanOptions {
+  fn record(&self, window: &str, options: &ScanOptions) {
+    if let Ok(mut recorded) = self.options.lock() {
+      recorded.insert(window.to_string(), options.clone());
+    }
+  }
+
+  fn get(&self, window: &str) -> Option<ScanOptions> {
+    self.options.lock().ok()?.get(window).cloned()
+  }
+
+  fn remove(&self, window: &str) {
+    if let Ok(mut recorded) = self.options.lock() {
+      recorded.remove(window);
+    }
+  }
 }
 
 #[derive(Debug, Serialize)]
@@ -45,6 +224,35 @@ struct ScanResult {
   root: String,
   label: String,
   files: Vec<ScanFile>,
+  truncated: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  truncated_reason: Option<ScanTruncation>,
+  warnings: Vec<ScanWarning>,
+  /// The named roots of a workspace opened with `scan_workspace`; `root` is then the first.
+  #[serde(skip_serializing_if = "Vec::is_empty")]
+  roots: Vec<WorkspaceRoot>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanStarted {
+  scan_id: String,
+  root: String,
+  label: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExtractedText {
+  category: &'static str,
+  text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchStarted {
+  search_id: String,
+  root: String,
 }
 
 fn home_dir() -> Option<PathBuf> {
@@ -77,6 +285,13 @@ fn config_file_path() -> Result<PathBuf, String> {
   Ok(home)
 }
 
+fn fulltext_index_dir() -> Result<PathBuf, String> {
+  let mut home = home_dir().ok_or_else(|| "无法获取用户主目录".to_string())?;
+  home.push(".rustreader");
+  home.push("index");
+  Ok(home)
+}
+
 fn recent_file_path() -> Result<PathBuf, String> {
   let mut home = home_dir().ok_or_else(|| "无法获取用户主目录".to_string())?;
   home.push(".rustreader");
@@ -84,12 +299,20 @@ fn recent_file_path() -> Result<PathBuf, String> {
   Ok(home)
 }
 
+/// Unix socket (or, on Windows, a file naming a loopback port) held by the running instance.
+fn instance_lock_path() -> Result<PathBuf, String> {
+  let mut home = home_dir().ok_or_else(|| "无法获取用户主目录".to_string())?;
+  home.push(".rustreader");
+  home.push("instance");
+  Ok(home)
+}
+
 fn sanitize_recent_entry(value: &str) -> Option<String> {
   let value = value.trim();
   if value.is_empty() {
     return None;
   }
-  let value = value.replace('\n', "").replace('\r', "").trim().to_string();
+  let value = value.replace(['\n', '\r'], "").trim().to_string();
   if value.is_empty() {
     return None;
   }
@@ -218,283 +441,656 @@ fn save_config_to_disk(config: &AppConfig) -> Result<(), String> {
   Ok(())
 }
 
-fn parse_cli_open_target(args: impl IntoIterator<Item = OsString>) -> Option<String> {
-  let mut iter = args.into_iter().peekable();
-  while let Some(arg) = iter.next() {
-    let arg_str = arg.to_string_lossy();
-    let arg_str = arg_str.trim();
-    if arg_str.is_empty() {
-      continue;
-    }
-
-    if arg_str == "--" {
-      if let Some(value) = iter.next() {
-        let value = value.to_string_lossy();
-        let value = value.trim();
-        if !value.is_empty() {
-          return Some(value.to_string());
-        }
+fn run_index_command(command: IndexArgs) -> i32 {
+  let root = match command.root {
+    Some(root) => root,
+    None => match std::env::current_dir() {
+      Ok(root) => root,
+      Err(error) => {
+        eprintln!("无法读取当前目录: {}", error);
+        return 1;
       }
-      continue;
-    }
+    },
+  };
 
-    if let Some(_) = arg_str.strip_prefix("--site-name=") {
-      continue;
+  match site_index::write_site_index(&root, command.out.as_deref()) {
+    Ok((out, total_files)) => {
+      println!("已生成 {}，包含文件数量: {}", out.display(), total_files);
+      0
     }
+    Err(error) => {
+      eprintln!("{}", error);
+      1
+    }
+  }
+}
 
-    if arg_str == "--site-name" {
-      iter.next();
-      continue;
+fn run_watch_command(command: IndexArgs) -> i32 {
+  let root = match command.root {
+    Some(root) => root,
+    None => PathBuf::from("."),
+  };
+  // Event paths are absolute, so compare them against absolute roots and outputs.
+  let root = match root.canonicalize() {
+    Ok(root) if root.is_dir() => root,
+    Ok(root) => {
+      eprintln!("不是目录: {}", root.display());
+      return 1;
     }
+    Err(error) => {
+      eprintln!("监控目录不存在: {} ({})", root.display(), error);
+      return 1;
+    }
+  };
+  let out = match command.out {
+    Some(out) if out.is_absolute() => out,
+    Some(out) => std::env::current_dir().map(|dir| dir.join(&out)).unwrap_or(out),
+    None => root.join(site_index::INDEX_FILE_NAME),
+  };
 
-    if let Some(value) = arg_str.strip_prefix("--open=") {
-      let value = value.trim();
-      if !value.is_empty() {
-        return Some(value.to_string());
-      }
-      continue;
+  println!("正在监听 {}", root.display());
+  match watch::watch_site_index(&root, &out) {
+    Ok(()) => 0,
+    Err(error) => {
+      eprintln!("{}", error);
+      1
     }
+  }
+}
 
-    if let Some(value) = arg_str.strip_prefix("--path=") {
-      let value = value.trim();
-      if !value.is_empty() {
-        return Some(value.to_string());
+fn run_export_command(command: ExportArgs) -> i32 {
+  let ui_files = site_ui_files();
+  let site_name = command.site_name.as_deref();
+  match site_export::export_site(&command.src, &command.dest, site_name, &ui_files) {
+    Ok(summary) => {
+      for path in &summary.skipped {
+        eprintln!("跳过与站点文件重名的 {}", path);
       }
-      continue;
+      println!("已导出 {} 个文件到 {}", summary.files, command.dest.display());
+      0
     }
+    Err(error) => {
+      eprintln!("{}", error);
+      1
+    }
+  }
+}
 
-    if arg_str == "--open" || arg_str == "-o" || arg_str == "--path" {
-      if let Some(value) = iter.next() {
-        let value = value.to_string_lossy();
-        let value = value.trim();
-        if !value.is_empty() {
-          return Some(value.to_string());
-        }
+/// The bundled web UI files a static site needs, keyed by their path below the site root.
+fn site_ui_files() -> Vec<(String, Vec<u8>)> {
+  let context = app_context();
+  let assets = context.assets();
+  let keys: Vec<String> = assets.iter().map(|(key, _)| key.into_owned()).collect();
+  keys
+    .into_iter()
+    .filter_map(|key| {
+      let path = key.trim_start_matches('/');
+      if path != "index.html" && !path.starts_with("assets/") {
+        return None;
       }
-      continue;
-    }
+      let content = assets.get(&key.as_str().into())?;
+      Some((path.to_string(), content.into_owned()))
+    })
+    .collect()
+}
 
-    if arg_str.starts_with("-psn_") {
-      continue;
-    }
+fn app_context() -> tauri::Context {
+  tauri::generate_context!()
+}
 
-    if arg_str.starts_with('-') {
-      continue;
+/// Handles the process arguments that do not start the desktop app: headless subcommands,
+/// help, version and usage errors. Returns the exit code, or `None` when the app should start.
+pub fn run_cli_command() -> Option<i32> {
+  let parsed = cli::parse(std::env::args_os().skip(1));
+  if matches!(parsed, Ok(Command::Open(_))) {
+    return None;
+  }
+  let has_console = attach_parent_console();
+  let command = match parsed {
+    Ok(command) => command,
+    Err(error) if has_console => {
+      eprintln!("{}", error);
+      return Some(2);
+    }
+    Err(error) => {
+      show_usage_error(&error);
+      return Some(2);
     }
+  };
+  match command {
+    Command::Open(_) => None,
+    Command::Index(args) => Some(run_index_command(args)),
+    Command::Watch(args) => Some(run_watch_command(args)),
+    Command::Export(args) => Some(run_export_command(args)),
+    Command::Help(text) => {
+      println!("{}", text);
+      Some(0)
+    }
+    Command::Version => {
+      println!("{} {}", APP_PREFIX, env!("CARGO_PKG_VERSION"));
+      Some(0)
+    }
+  }
+}
 
-    return Some(arg_str.to_string());
+/// Release builds on Windows are GUI programs that start without a console, so the output of
+/// the subcommands would go nowhere. Attaches to the console of the shell that ran this process
+/// and returns whether there is one to write to.
+#[cfg(windows)]
+fn attach_parent_console() -> bool {
+  use windows_sys::Win32::Foundation::{GetLastError, ERROR_ACCESS_DENIED};
+  use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+  // SAFETY: both calls take plain values and touch no memory of this process.
+  unsafe {
+    // Debug builds are console programs, and attaching them again is denied.
+    AttachConsole(ATTACH_PARENT_PROCESS) != 0 || GetLastError() == ERROR_ACCESS_DENIED
   }
-  None
 }
 
-fn parse_cli_site_name(args: impl IntoIterator<Item = OsString>) -> Option<String> {
-  let mut iter = args.into_iter();
-  while let Some(arg) = iter.next() {
-    let arg_str = arg.to_string_lossy();
-    let arg_str = arg_str.trim();
-    if arg_str.is_empty() {
-      continue;
-    }
+#[cfg(not(windows))]
+fn attach_parent_console() -> bool {
+  true
+}
 
-    if arg_str == "--" {
-      break;
-    }
+/// Usage errors of an invocation without a console, such as a shortcut with a typo.
+fn show_usage_error(error: &str) {
+  rfd::MessageDialog::new()
+    .set_level(rfd::MessageLevel::Error)
+    .set_title(APP_PREFIX)
+    .set_description(error)
+    .set_buttons(rfd::MessageButtons::Ok)
+    .show();
+}
 
-    if let Some(value) = arg_str.strip_prefix("--site-name=") {
-      let value = value.trim();
-      if !value.is_empty() {
-        return Some(value.to_string());
-      }
-      continue;
+/// The desktop options of this process; the arguments were already checked by
+/// [`run_cli_command`].
+fn cli_open_args() -> OpenArgs {
+  match cli::parse(std::env::args_os().skip(1)) {
+    Ok(Command::Open(args)) => args,
+    _ => OpenArgs::default(),
+  }
+}
+
+fn emit_scan_progress(app: &tauri::AppHandle, window: &str, payload: ScanProgressEvent) {
+  let _ = app.emit_to(window, SCAN_PROGRESS_EVENT, payload);
+}
+
+fn emit_scan_batch(app: &tauri::AppHandle, window: &str, scan_id: &str, files: Vec<ScanFile>) {
+  let _ = app.emit_to(
+    window,
+    SCAN_BATCH_EVENT,
+    ScanBatchEvent {
+      scan_id: scan_id.to_string(),
+      files,
+    },
+  );
+}
+
+fn emit_scan_finished(app: &tauri::AppHandle, window: &str, payload: ScanFinishedEvent) {
+  let _ = app.emit_to(window, SCAN_FINISHED_EVENT, payload);
+}
+
+struct PendingBatch {
+  files: Vec<ScanFile>,
+  last_flush: Instant,
+  /// Copies of the batches already streamed, handed to the full-text index afterwards.
+  streamed: Vec<ScanFile>,
+}
+
+/// Forwards walker output to the window that started the scan: progress always, matched files
+/// either collected for the command result or streamed as `SCAN_BATCH_EVENT` batches. Either
+/// way `into_files` returns everything that matched.
+struct EventScanSink<'a> {
+  app: &'a tauri::AppHandle,
+  window: &'a str,
+  scan_id: Option<&'a str>,
+  stream: bool,
+  pending: Mutex<PendingBatch>,
+}
+
+impl<'a> EventScanSink<'a> {
+  fn new(
+    app: &'a tauri::AppHandle,
+    window: &'a str,
+    scan_id: Option<&'a str>,
+    stream: bool,
+  ) -> Self {
+    Self {
+      app,
+      window,
+      scan_id,
+      stream,
+      pending: Mutex::new(PendingBatch {
+        files: Vec::new(),
+        last_flush: Instant::now(),
+        streamed: Vec::new(),
+      }),
     }
+  }
 
-    if arg_str == "--site-name" {
-      if let Some(value) = iter.next() {
-        let value = value.to_string_lossy();
-        let value = value.trim();
-        if !value.is_empty() {
-          return Some(value.to_string());
+  fn into_files(self) -> Vec<ScanFile> {
+    let pending = match self.pending.into_inner() {
+      Ok(pending) => pending,
+      Err(poisoned) => poisoned.into_inner(),
+    };
+    match (self.stream, self.scan_id) {
+      (true, Some(scan_id)) => {
+        let mut streamed = pending.streamed;
+        if !pending.files.is_empty() {
+          streamed.extend(pending.files.iter().cloned());
+          emit_scan_batch(self.app, self.window, scan_id, pending.files);
         }
+        streamed
       }
-      continue;
+      _ => pending.files,
     }
   }
-  None
 }
 
-fn categorize_file(path: &Path) -> Option<&'static str> {
-  let name_lower = path.file_name()?.to_string_lossy().to_lowercase();
-  if name_lower.ends_with(".mm.md") {
-    return Some("mindmap");
-  }
-  if name_lower.ends_with(".ppt.md") {
-    return Some("marpit");
+impl ScanSink for EventScanSink<'_> {
+  fn progress(&self, stage: &'static str, counts: ScanCounts, current_path: &Path) {
+    emit_scan_progress(
+      self.app,
+      self.window,
+      ScanProgressEvent {
+        scan_id: self.scan_id.map(str::to_string),
+        stage,
+        scanned_dirs: counts.scanned_dirs,
+        scanned_files: counts.scanned_files,
+        matched_files: counts.matched_files,
+        skipped: counts.skipped,
+        current_path: current_path.to_string_lossy().into_owned(),
+      },
+    );
   }
 
-  let ext = path.extension()?.to_string_lossy().to_lowercase();
-  match ext.as_str() {
-    "png" | "jpg" | "jpeg" | "gif" | "webp" => Some("images"),
-    "mp4" | "webm" | "ogv" | "m4v" => Some("video"),
-    "mp3" | "wav" | "m4a" | "ogg" | "oga" | "flac" | "aac" => Some("audio"),
-    "md" | "markdown" => Some("markdown"),
-    "drawio" => Some("drawio"),
-    "pdf" => Some("pdf"),
-    "docx" => Some("word"),
-    "xlsx" => Some("excel"),
-    "txt" => Some("text"),
-    "pptx" => Some("slides"),
-    _ => None,
+  fn files(&self, files: Vec<ScanFile>) {
+    let Ok(mut pending) = self.pending.lock() else {
+      return;
+    };
+    pending.files.extend(files);
+
+    let Some(scan_id) = self.scan_id.filter(|_| self.stream) else {
+      return;
+    };
+    if pending.files.len() >= SCAN_BATCH_SIZE
+      || pending.last_flush.elapsed() >= SCAN_BATCH_INTERVAL
+    {
+      let batch = std::mem::take(&mut pending.files);
+      pending.last_flush = Instant::now();
+      pending.streamed.extend(batch.iter().cloned());
+      drop(pending);
+      emit_scan_batch(self.app, self.window, scan_id, batch);
+    }
   }
 }
 
-fn emit_scan_progress(app: &tauri::AppHandle, payload: ScanProgressEvent) {
-  let _ = app.emit(SCAN_PROGRESS_EVENT, payload);
+fn path_label(path: &Path) -> String {
+  path
+    .file_name()
+    .map(|name| name.to_string_lossy().into_owned())
+    .unwrap_or_else(|| path.display().to_string())
+}
+
+fn resolve_scan_options(options: Option<ScanOptions>) -> ScanOptions {
+  options
+    .or_else(|| load_config_from_disk().ok().and_then(|config| config.scan_options))
+    .unwrap_or_default()
 }
 
 fn scan_supported_files(
   app: &tauri::AppHandle,
+  window: &str,
   scan_id: Option<&str>,
   root: &Path,
-) -> Vec<ScanFile> {
-  let mut stack: Vec<PathBuf> = vec![root.to_path_buf()];
+  options: &ScanOptions,
+) -> Option<ScanResult> {
+  let registry = app.state::<ScanRegistry>();
+  let cancel_flag = match scan_id {
+    Some(scan_id) => registry.register(scan_id),
+    None => Arc::new(AtomicBool::new(false)),
+  };
+  let collected = collect_supported_files(app, window, scan_id, root, options, &cancel_flag);
+  if let Some(scan_id) = scan_id {
+    registry.unregister(scan_id);
+  }
+  let (files, outcome) = collected?;
+  watch_scanned_folder(app, window, &[root.to_path_buf()], options);
+
+  Some(ScanResult {
+    root: root.to_string_lossy().into_owned(),
+    label: path_label(root),
+    files,
+    truncated: outcome.truncated.is_some(),
+    truncated_reason: outcome.truncated,
+    warnings: outcome.warnings,
+    roots: Vec::new(),
+  })
+}
+
+/// Walks `root` for a command result and refreshes its full-text index. `None` when the scan
+/// was cancelled.
+fn collect_supported_files(
+  app: &tauri::AppHandle,
+  window: &str,
+  scan_id: Option<&str>,
+  root: &Path,
+  options: &ScanOptions,
+  cancel_flag: &AtomicBool,
+) -> Option<(Vec<ScanFile>, ScanOutcome)> {
+  app.state::<TreeScanOptions>().record(window, options);
+  let sink = EventScanSink::new(app, window, scan_id, false);
+  let outcome =
+    scan::walk_supported_files(root, options, scan::default_scan_threads(), cancel_flag, &sink)?;
+  let mut files = sink.into_files();
+  scan::sort_scan_files(&mut files);
+  refresh_fulltext_index(app, root, files.clone(), &outcome);
+  Some((files, outcome))
+}
+
+/// Scans the roots of a workspace one after another into a single result whose virtual paths
+/// start with the root names. A root that is a file carries its category.
+fn scan_workspace_roots(
+  app: &tauri::AppHandle,
+  window: &str,
+  scan_id: Option<&str>,
+  roots: Vec<(WorkspaceRoot, Option<&'static str>)>,
+  options: &ScanOptions,
+) -> Option<ScanResult> {
+  let registry = app.state::<ScanRegistry>();
+  let cancel_flag = match scan_id {
+    Some(scan_id) => registry.register(scan_id),
+    None => Arc::new(AtomicBool::new(false)),
+  };
+
   let mut files = Vec::new();
-  let scan_id_owned = scan_id.map(str::to_string);
-  let mut scanned_dirs: u64 = 0;
-  let mut scanned_files: u64 = 0;
-  let mut matched_files: u64 = 0;
-  let mut last_emit = Instant::now();
-  let emit_interval = Duration::from_millis(120);
-
-  emit_scan_progress(
-    app,
-    ScanProgressEvent {
-      scan_id: scan_id_owned.clone(),
-      stage: "start",
-      scanned_dirs,
-      scanned_files,
-      matched_files,
-      current_path: root.to_string_lossy().into_owned(),
-    },
-  );
+  let mut folders = Vec::new();
+  let mut truncated = None;
+  let mut warnings = Vec::new();
+  let mut cancelled = false;
+  let started = Instant::now();
+  for (root, category) in &roots {
+    let path = Path::new(&root.path);
+    match category {
+      Some(category) => {
+        let mut file = single_scan_file(path, category);
+        file.virtual_path = root.name.clone();
+        files.push(file);
+      }
+      None => {
+        let root_options = workspace::remaining_options(options, files.len(), started.elapsed());
+        let Some((mut root_files, outcome)) =
+          collect_supported_files(app, window, scan_id, path, &root_options, &cancel_flag)
+        else {
+          cancelled = true;
+          break;
+        };
+        workspace::prefix_files(&root.name, &mut root_files);
+        files.extend(root_files);
+        folders.push(path.to_path_buf());
+        truncated = truncated.or(outcome.truncated);
+        warnings.extend(outcome.warnings);
+      }
+    }
+  }
+  if let Some(scan_id) = scan_id {
+    registry.unregister(scan_id);
+  }
+  if cancelled {
+    return None;
+  }
+  watch_scanned_folder(app, window, &folders, options);
 
-  while let Some(dir) = stack.pop() {
-    scanned_dirs = scanned_dirs.saturating_add(1);
-    if last_emit.elapsed() >= emit_interval {
-      emit_scan_progress(
-        app,
-        ScanProgressEvent {
-          scan_id: scan_id_owned.clone(),
-          stage: "progress",
-          scanned_dirs,
-          scanned_files,
-          matched_files,
-          current_path: dir.to_string_lossy().into_owned(),
-        },
-      );
-      last_emit = Instant::now();
+  let roots: Vec<WorkspaceRoot> = roots.into_iter().map(|(root, _)| root).collect();
+  let label = roots.iter().map(|root| root.name.as_str()).collect::<Vec<_>>().join(", ");
+  Some(ScanResult {
+    root: roots.first().map(|root| root.path.clone()).unwrap_or_default(),
+    label,
+    files,
+    truncated: truncated.is_some(),
+    truncated_reason: truncated,
+    warnings,
+    roots,
+  })
+}
+
+fn watch_scanned_folder(
+  app: &tauri::AppHandle,
+  window: &str,
+  roots: &[PathBuf],
+  options: &ScanOptions,
+) {
+  if let Err(error) = app.state::<FolderWatchState>().watch(app, window, roots, options) {
+    log::warn!("{}", error);
+  }
+}
+
+fn refresh_fulltext_index(
+  app: &tauri::AppHandle,
+  root: &Path,
+  files: Vec<ScanFile>,
+  outcome: &ScanOutcome,
+) {
+  let complete = outcome.truncated.is_none();
+  if let Err(error) = app.state::<FullTextState>().refresh(root, files, complete) {
+    log::warn!("{}", error);
+  }
+}
+
+/// Category of a file opened on its own; its content is always sniffed.
+fn opened_file_category(path: &Path) -> Result<&'static str, String> {
+  let by_name = categorize_file(&path_label(path));
+  let category = sniff::detect_category(path, by_name).unwrap_or(by_name);
+  category.ok_or_else(|| "不支持打开该文件类型（仅支持可预览的文件类型）".to_string())
+}
+
+fn single_scan_file(path: &Path, category: &str) -> ScanFile {
+  let mut file = ScanFile::new(path_label(path), path.to_string_lossy().into_owned(), category);
+  file.inspect_container(path);
+  file.detect_language(path, &file_types::current());
+  if let Ok(metadata) = std::fs::metadata(path) {
+    file.apply_metadata(&metadata);
+  }
+  file
+}
+
+fn single_file_scan_result(
+  app: &tauri::AppHandle,
+  window: &str,
+  path: &Path,
+  category: &str,
+) -> ScanResult {
+  app.state::<FolderWatchState>().stop(window);
+  ScanResult {
+    root: path.to_string_lossy().into_owned(),
+    label: path_label(path),
+    files: vec![single_scan_file(path, category)],
+    truncated: false,
+    truncated_reason: None,
+    warnings: Vec::new(),
+    roots: Vec::new(),
+  }
+}
+
+fn stream_supported_files(
+  app: &tauri::AppHandle,
+  window: &str,
+  scan_id: &str,
+  root: &Path,
+  options: &ScanOptions,
+  cancel_flag: &AtomicBool,
+) -> Option<ScanOutcome> {
+  app.state::<TreeScanOptions>().record(window, options);
+  let sink = EventScanSink::new(app, window, Some(scan_id), true);
+  let outcome =
+    scan::walk_supported_files(root, options, scan::default_scan_threads(), cancel_flag, &sink);
+  if let Some(outcome) = &outcome {
+    let files = sink.into_files();
+    watch_scanned_folder(app, window, &[root.to_path_buf()], options);
+    refresh_fulltext_index(app, root, files, outcome);
+  }
+  outcome
+}
+
+/// One root of a content search; `name` is set when it is part of a workspace.
+struct SearchTarget {
+  name: Option<String>,
+  path: PathBuf,
+  single_file: Option<&'static str>,
+}
+
+struct PendingMatches {
+  matches: Vec<SearchMatch>,
+  last_flush: Instant,
+}
+
+/// Searches the files the walker hands over, on the walker's own worker threads, and streams
+/// matching lines as `SEARCH_MATCHES_EVENT` batches to the window that started the search.
+struct ContentSearchSink<'a> {
+  app: &'a tauri::AppHandle,
+  window: &'a str,
+  search_id: &'a str,
+  matcher: &'a ContentMatcher,
+  stop_flag: &'a AtomicBool,
+  max_matches: u64,
+  pending: Mutex<PendingMatches>,
+  searched_files: AtomicU64,
+  matched_files: AtomicU64,
+  match_count: AtomicU64,
+  skipped: AtomicU64,
+  truncated: AtomicBool,
+}
+
+impl<'a> ContentSearchSink<'a> {
+  fn new(
+    app: &'a tauri::AppHandle,
+    window: &'a str,
+    search_id: &'a str,
+    matcher: &'a ContentMatcher,
+    stop_flag: &'a AtomicBool,
+    max_matches: u64,
+  ) -> Self {
+    Self {
+      app,
+      window,
+      search_id,
+      matcher,
+      stop_flag,
+      max_matches,
+      pending: Mutex::new(PendingMatches {
+        matches: Vec::new(),
+        last_flush: Instant::now(),
+      }),
+      searched_files: AtomicU64::new(0),
+      matched_files: AtomicU64::new(0),
+      match_count: AtomicU64::new(0),
+      skipped: AtomicU64::new(0),
+      truncated: AtomicBool::new(false),
     }
-    let entries = match std::fs::read_dir(&dir) {
-      Ok(entries) => entries,
-      Err(_) => continue,
+  }
+
+  fn emit_matches(&self, matches: Vec<SearchMatch>) {
+    let _ = self.app.emit_to(
+      self.window,
+      SEARCH_MATCHES_EVENT,
+      SearchMatchesEvent {
+        search_id: self.search_id.to_string(),
+        matches,
+      },
+    );
+  }
+
+  /// Keeps as many of `matches` as the `max_matches` budget allows; reaching the budget stops
+  /// the walk through the shared flag.
+  fn claim_matches(&self, mut matches: Vec<SearchMatch>) -> Vec<SearchMatch> {
+    let wanted = matches.len() as u64;
+    let previous = self.match_count.fetch_add(wanted, Ordering::Relaxed);
+    let allowed = self.max_matches.saturating_sub(previous).min(wanted);
+    matches.truncate(allowed as usize);
+    if previous + wanted >= self.max_matches {
+      self.truncated.store(true, Ordering::Relaxed);
+      self.stop_flag.store(true, Ordering::Relaxed);
+    }
+    matches
+  }
+
+  fn push_matches(&self, matches: Vec<SearchMatch>) {
+    let Ok(mut pending) = self.pending.lock() else {
+      return;
     };
+    pending.matches.extend(matches);
+    if pending.matches.len() >= SEARCH_BATCH_SIZE
+      || pending.last_flush.elapsed() >= SCAN_BATCH_INTERVAL
+    {
+      let batch = std::mem::take(&mut pending.matches);
+      pending.last_flush = Instant::now();
+      drop(pending);
+      self.emit_matches(batch);
+    }
+  }
 
-    for entry in entries {
-      let entry = match entry {
-        Ok(entry) => entry,
-        Err(_) => continue,
-      };
+  fn finish(self, stage: &'static str, root: String) {
+    let matches = match self.pending.into_inner() {
+      Ok(pending) => pending.matches,
+      Err(poisoned) => poisoned.into_inner().matches,
+    };
+    if !matches.is_empty() {
+      let _ = self.app.emit_to(
+        self.window,
+        SEARCH_MATCHES_EVENT,
+        SearchMatchesEvent {
+          search_id: self.search_id.to_string(),
+          matches,
+        },
+      );
+    }
+    let match_count = self.match_count.load(Ordering::Relaxed).min(self.max_matches);
+    let _ = self.app.emit_to(
+      self.window,
+      SEARCH_FINISHED_EVENT,
+      SearchFinishedEvent {
+        search_id: self.search_id.to_string(),
+        stage,
+        root,
+        searched_files: self.searched_files.load(Ordering::Relaxed),
+        matched_files: self.matched_files.load(Ordering::Relaxed),
+        match_count,
+        skipped: self.skipped.load(Ordering::Relaxed),
+        truncated: self.truncated.load(Ordering::Relaxed),
+      },
+    );
+  }
+}
 
-      let file_type = match entry.file_type() {
-        Ok(file_type) => file_type,
-        Err(_) => continue,
-      };
+impl ScanSink for ContentSearchSink<'_> {
+  fn progress(&self, _stage: &'static str, _counts: ScanCounts, _current_path: &Path) {}
 
-      let path = entry.path();
-      if file_type.is_dir() {
-        if last_emit.elapsed() >= emit_interval {
-          emit_scan_progress(
-            app,
-            ScanProgressEvent {
-              scan_id: scan_id_owned.clone(),
-              stage: "progress",
-              scanned_dirs,
-              scanned_files,
-              matched_files,
-              current_path: path.to_string_lossy().into_owned(),
-            },
-          );
-          last_emit = Instant::now();
-        }
-        stack.push(path);
-        continue;
+  fn files(&self, files: Vec<ScanFile>) {
+    for file in files {
+      if self.stop_flag.load(Ordering::Relaxed) {
+        return;
       }
-      if !file_type.is_file() {
+      if !self.matcher.wants(&file.category) {
         continue;
       }
-
-      scanned_files = scanned_files.saturating_add(1);
-      let Some(category) = categorize_file(&path) else {
-        if last_emit.elapsed() >= emit_interval {
-          emit_scan_progress(
-            app,
-            ScanProgressEvent {
-              scan_id: scan_id_owned.clone(),
-              stage: "progress",
-              scanned_dirs,
-              scanned_files,
-              matched_files,
-              current_path: path.to_string_lossy().into_owned(),
-            },
-          );
-          last_emit = Instant::now();
+      let matches = match self.matcher.search_file(&file, search::MAX_MATCHES_PER_FILE) {
+        Ok(Some(matches)) => matches,
+        Ok(None) | Err(_) => {
+          self.skipped.fetch_add(1, Ordering::Relaxed);
+          continue;
         }
-        continue;
       };
-      matched_files = matched_files.saturating_add(1);
-
-      let rel = match path.strip_prefix(root) {
-        Ok(rel) => rel,
-        Err(_) => continue,
-      };
-
-      let abs_path = path.to_string_lossy().into_owned();
-      files.push(ScanFile {
-        virtual_path: rel.to_string_lossy().replace('\\', "/"),
-        abs_path: abs_path.clone(),
-        category: category.to_string(),
-      });
-
-      if last_emit.elapsed() >= emit_interval {
-        emit_scan_progress(
-          app,
-          ScanProgressEvent {
-            scan_id: scan_id_owned.clone(),
-            stage: "progress",
-            scanned_dirs,
-            scanned_files,
-            matched_files,
-            current_path: abs_path,
-          },
-        );
-        last_emit = Instant::now();
+      self.searched_files.fetch_add(1, Ordering::Relaxed);
+      if matches.is_empty() {
+        continue;
+      }
+      let matches = self.claim_matches(matches);
+      if !matches.is_empty() {
+        self.matched_files.fetch_add(1, Ordering::Relaxed);
+        self.push_matches(matches);
       }
     }
   }
-
-  emit_scan_progress(
-    app,
-    ScanProgressEvent {
-      scan_id: scan_id_owned,
-      stage: "done",
-      scanned_dirs,
-      scanned_files,
-      matched_files,
-      current_path: root.to_string_lossy().into_owned(),
-    },
-  );
-
-  files.sort_by(|a, b| a.virtual_path.cmp(&b.virtual_path));
-  files
 }
 
 fn normalize_file_url_to_path(raw: &str) -> Cow<'_, str> {
@@ -517,31 +1113,177 @@ fn normalize_file_url_to_path(raw: &str) -> Cow<'_, str> {
   Cow::Owned(without_host.to_string())
 }
 
+/// The open targets, selection and site name of this invocation, for the main window or for the
+/// instance that is already running. An anchor given as `path#anchor` applies unless
+/// `--anchor` is set.
+fn cli_open_request(args: &OpenArgs) -> OpenRequest {
+  let mut anchor = args.anchor.clone();
+  let open_targets = args
+    .targets
+    .iter()
+    .map(|target| {
+      let target = absolute_cli_target(target);
+      match split_target_anchor(&target) {
+        Some((path, target_anchor)) => {
+          anchor.get_or_insert_with(|| target_anchor.to_string());
+          path.to_string()
+        }
+        None => target,
+      }
+    })
+    .collect();
+  OpenRequest {
+    open_targets,
+    select: args.select.as_deref().map(cli_select_path),
+    anchor,
+    site_name: args.site_name.clone(),
+  }
+}
+
+/// `guide.md#install` names a heading of `guide.md`, unless a file by that whole name exists.
+fn split_target_anchor(target: &str) -> Option<(&str, &str)> {
+  if Path::new(target).exists() {
+    return None;
+  }
+  cli::split_anchor(target).filter(|(path, _)| Path::new(path).exists())
+}
+
+/// A selection relative to the opened folder is kept as given; `file://` links become paths.
+fn cli_select_path(select: &str) -> String {
+  let path = normalize_file_url_to_path(select);
+  if Path::new(path.as_ref()).is_absolute() {
+    path.into_owned()
+  } else {
+    select.replace('\\', "/")
+  }
+}
+
+/// Resolves a relative target against this process's working directory, which a running
+/// instance does not share.
+fn absolute_cli_target(target: &str) -> String {
+  let path = PathBuf::from(normalize_file_url_to_path(target).as_ref());
+  if path.is_absolute() {
+    return path.to_string_lossy().into_owned();
+  }
+  match std::env::current_dir() {
+    Ok(dir) => dir.join(path).to_string_lossy().into_owned(),
+    Err(_) => target.to_string(),
+  }
+}
+
+/// The first path the calling window should open: the command line's for the main window,
+/// the one passed to `open_in_new_window` for the others.
 #[tauri::command]
-fn get_cli_open_target() -> Option<String> {
-  parse_cli_open_target(std::env::args_os().skip(1))
+fn get_cli_open_target(
+  window: tauri::WebviewWindow,
+  targets: tauri::State<'_, WindowTargets>,
+) -> Option<String> {
+  targets.get(window.label()).open_targets.into_iter().next()
 }
 
+/// Every path the calling window should open, for `scan_workspace`.
 #[tauri::command]
-fn get_cli_site_name() -> Option<String> {
-  parse_cli_site_name(std::env::args_os().skip(1))
+fn get_cli_open_targets(
+  window: tauri::WebviewWindow,
+  targets: tauri::State<'_, WindowTargets>,
+) -> Vec<String> {
+  targets.get(window.label()).open_targets
 }
 
+/// The document the calling window should preselect once its paths are scanned.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CliSelection {
+  select: Option<String>,
+  anchor: Option<String>,
+}
+
+/// The `--select` document and `--anchor` heading of the calling window, alongside
+/// `get_cli_open_target`; both are empty for windows opened from the UI.
 #[tauri::command]
-fn set_app_window_title(app: tauri::AppHandle, site_name: String) -> Result<(), String> {
-  let title = build_window_title(&site_name);
-  for window in app.webview_windows().values() {
-    let _ = window.set_title(&title);
+fn get_cli_selection(
+  window: tauri::WebviewWindow,
+  targets: tauri::State<'_, WindowTargets>,
+) -> CliSelection {
+  let request = targets.get(window.label());
+  CliSelection {
+    select: request.select,
+    anchor: request.anchor,
   }
-  Ok(())
 }
 
 #[tauri::command]
+fn get_cli_site_name(
+  window: tauri::WebviewWindow,
+  targets: tauri::State<'_, WindowTargets>,
+) -> Option<String> {
+  targets.get(window.label()).site_name
+}
+
+#[tauri::command]
+fn set_app_window_title(window: tauri::WebviewWindow, site_name: String) -> Result<(), String> {
+  window
+    .set_title(&build_window_title(&site_name))
+    .map_err(|error| format!("设置窗口标题失败: {}", error))
+}
+
+/// Opens another window that shows `path` under `site_name`, with its own scans, watches and
+/// title, and returns its label. Without a path the window starts empty.
+#[tauri::command(async, rename_all = "snake_case")]
+fn open_in_new_window(
+  app: tauri::AppHandle,
+  targets: tauri::State<'_, WindowTargets>,
+  path: Option<String>,
+  site_name: Option<String>,
+) -> Result<String, String> {
+  let open_targets = path
+    .map(|path| path.trim().to_string())
+    .filter(|path| !path.is_empty())
+    .into_iter()
+    .collect();
+  let site_name = site_name
+    .map(|name| name.trim().to_string())
+    .filter(|name| !name.is_empty());
+  let title = build_window_title(site_name.as_deref().unwrap_or(site_export::DEFAULT_SITE_NAME));
+
+  let label = targets.next_label();
+  targets.insert(
+    &label,
+    OpenRequest {
+      open_targets,
+      site_name,
+      ..OpenRequest::default()
+    },
+  );
+  let built = tauri::WebviewWindowBuilder::new(&app, &label, tauri::WebviewUrl::default())
+    .title(title)
+    .inner_size(1280.0, 800.0)
+    .build();
+  if let Err(error) = built {
+    targets.remove(&label);
+    return Err(format!("创建窗口失败: {}", error));
+  }
+  Ok(label)
+}
+
+/// Drops what a closed window held: its open target, its folder and file watchers and the
+/// options its tree was scanned with.
+fn forget_window(app: &tauri::AppHandle, label: &str) {
+  app.state::<WindowTargets>().remove(label);
+  app.state::<FolderWatchState>().stop(label);
+  app.state::<FileWatchState>().stop(label);
+  app.state::<TreeScanOptions>().remove(label);
+}
+
+#[tauri::command(async, rename_all = "snake_case")]
 fn scan_path(
   app: tauri::AppHandle,
+  window: tauri::WebviewWindow,
   path: String,
   scan_id: Option<String>,
+  options: Option<ScanOptions>,
 ) -> Result<Option<ScanResult>, String> {
+  let options = resolve_scan_options(options);
   let raw = path.trim();
   if raw.is_empty() {
     return Ok(None);
@@ -555,48 +1297,77 @@ fn scan_path(
 
   if abs_path.is_dir() {
     let _ = record_recent_path(&abs_path);
-    let label = abs_path
-      .file_name()
-      .map(|name| name.to_string_lossy().into_owned())
-      .unwrap_or_else(|| abs_path.display().to_string());
-
-    return Ok(Some(ScanResult {
-      root: abs_path.to_string_lossy().into_owned(),
-      label,
-      files: scan_supported_files(&app, scan_id.as_deref(), &abs_path),
-    }));
+    return Ok(scan_supported_files(&app, window.label(), scan_id.as_deref(), &abs_path, &options));
   }
 
   if abs_path.is_file() {
-    let Some(category) = categorize_file(&abs_path) else {
-      return Err("不支持打开该文件类型（仅支持可预览的文件扩展名）".to_string());
-    };
+    let category = opened_file_category(&abs_path)?;
     let _ = record_recent_path(&abs_path);
+    return Ok(Some(single_file_scan_result(&app, window.label(), &abs_path, category)));
+  }
+
+  Err("路径不是文件或文件夹".to_string())
+}
 
-    let virtual_path = abs_path
-      .file_name()
-      .map(|name| name.to_string_lossy().into_owned())
-      .unwrap_or_else(|| abs_path.display().to_string());
+/// Opens several folders or files as one workspace: each root becomes a top-level node named
+/// as `workspace::workspace_roots` names it, with its files' virtual paths below that name.
+/// A single path is scanned as `scan_path` would.
+#[tauri::command(async, rename_all = "snake_case")]
+fn scan_workspace(
+  app: tauri::AppHandle,
+  window: tauri::WebviewWindow,
+  paths: Vec<String>,
+  scan_id: Option<String>,
+  options: Option<ScanOptions>,
+) -> Result<Option<ScanResult>, String> {
+  let options = resolve_scan_options(options);
+  let mut abs_paths = Vec::new();
+  for path in &paths {
+    let raw = path.trim();
+    if raw.is_empty() {
+      continue;
+    }
+    let raw = normalize_file_url_to_path(raw);
+    let abs_path = PathBuf::from(raw.as_ref())
+      .canonicalize()
+      .map_err(|error| format!("路径不存在或无法访问: {} ({})", raw, error))?;
+    abs_paths.push(abs_path);
+  }
 
-    return Ok(Some(ScanResult {
-      root: abs_path.to_string_lossy().into_owned(),
-      label: virtual_path.clone(),
-      files: vec![ScanFile {
-        virtual_path,
-        abs_path: abs_path.to_string_lossy().into_owned(),
-        category: category.to_string(),
-      }],
-    }));
+  let roots = workspace::workspace_roots(&abs_paths);
+  if roots.len() <= 1 {
+    let Some(root) = roots.into_iter().next() else {
+      return Ok(None);
+    };
+    return scan_path(app, window, root.path, scan_id, Some(options));
   }
 
-  Err("路径不是文件或文件夹".to_string())
+  let mut categorized = Vec::with_capacity(roots.len());
+  for root in roots {
+    let path = Path::new(&root.path);
+    let category = if path.is_dir() {
+      None
+    } else if path.is_file() {
+      Some(opened_file_category(path)?)
+    } else {
+      return Err(format!("路径不是文件或文件夹: {}", root.path));
+    };
+    categorized.push((root, category));
+  }
+  for (root, _) in &categorized {
+    let _ = record_recent_path(Path::new(&root.path));
+  }
+  Ok(scan_workspace_roots(&app, window.label(), scan_id.as_deref(), categorized, &options))
 }
 
-#[tauri::command]
+#[tauri::command(async, rename_all = "snake_case")]
 fn pick_and_scan_folder(
   app: tauri::AppHandle,
+  window: tauri::WebviewWindow,
   scan_id: Option<String>,
+  options: Option<ScanOptions>,
 ) -> Result<Option<ScanResult>, String> {
+  let options = resolve_scan_options(options);
   let Some(root) = rfd::FileDialog::new().pick_folder() else {
     return Ok(None);
   };
@@ -606,24 +1377,17 @@ fn pick_and_scan_folder(
 
   let abs_root = root.canonicalize().unwrap_or(root);
   let _ = record_recent_path(&abs_root);
-
-  let label = abs_root
-    .file_name()
-    .map(|name| name.to_string_lossy().into_owned())
-    .unwrap_or_else(|| abs_root.display().to_string());
-
-  Ok(Some(ScanResult {
-    root: abs_root.to_string_lossy().into_owned(),
-    label,
-    files: scan_supported_files(&app, scan_id.as_deref(), &abs_root),
-  }))
+  Ok(scan_supported_files(&app, window.label(), scan_id.as_deref(), &abs_root, &options))
 }
 
-#[tauri::command]
+#[tauri::command(async, rename_all = "snake_case")]
 fn pick_and_scan_file(
   app: tauri::AppHandle,
+  window: tauri::WebviewWindow,
   scan_id: Option<String>,
+  options: Option<ScanOptions>,
 ) -> Result<Option<ScanResult>, String> {
+  let options = resolve_scan_options(options);
   let Some(input) = rfd::FileDialog::new().pick_file() else {
     return Ok(None);
   };
@@ -631,41 +1395,323 @@ fn pick_and_scan_file(
   let abs_path = input.canonicalize().unwrap_or(input);
   if abs_path.is_dir() {
     let _ = record_recent_path(&abs_path);
-    let label = abs_path
-      .file_name()
-      .map(|name| name.to_string_lossy().into_owned())
-      .unwrap_or_else(|| abs_path.display().to_string());
-
-    return Ok(Some(ScanResult {
-      root: abs_path.to_string_lossy().into_owned(),
-      label,
-      files: scan_supported_files(&app, scan_id.as_deref(), &abs_path),
-    }));
+    return Ok(scan_supported_files(&app, window.label(), scan_id.as_deref(), &abs_path, &options));
   }
 
   if abs_path.is_file() {
-    let Some(category) = categorize_file(&abs_path) else {
-      return Err("不支持打开该文件类型（仅支持可预览的文件扩展名）".to_string());
-    };
+    let category = opened_file_category(&abs_path)?;
     let _ = record_recent_path(&abs_path);
+    return Ok(Some(single_file_scan_result(&app, window.label(), &abs_path, category)));
+  }
 
-    let virtual_path = abs_path
-      .file_name()
-      .map(|name| name.to_string_lossy().into_owned())
-      .unwrap_or_else(|| abs_path.display().to_string());
+  Err("路径不是文件或文件夹".to_string())
+}
 
-    return Ok(Some(ScanResult {
-      root: abs_path.to_string_lossy().into_owned(),
-      label: virtual_path.clone(),
-      files: vec![ScanFile {
-        virtual_path,
-        abs_path: abs_path.to_string_lossy().into_owned(),
-        category: category.to_string(),
-      }],
-    }));
+#[tauri::command(rename_all = "snake_case")]
+fn start_scan(
+  app: tauri::AppHandle,
+  window: tauri::WebviewWindow,
+  registry: tauri::State<'_, ScanRegistry>,
+  path: String,
+  scan_id: String,
+  options: Option<ScanOptions>,
+) -> Result<Option<ScanStarted>, String> {
+  let options = resolve_scan_options(options);
+  let raw = path.trim();
+  if raw.is_empty() {
+    return Ok(None);
+  }
+  let scan_id = scan_id.trim().to_string();
+  if scan_id.is_empty() {
+    return Err("缺少扫描 ID".to_string());
   }
 
-  Err("路径不是文件或文件夹".to_string())
+  let raw = normalize_file_url_to_path(raw);
+  let input_path = PathBuf::from(raw.as_ref());
+  let abs_path = input_path
+    .canonicalize()
+    .map_err(|error| format!("路径不存在或无法访问: {}", error))?;
+
+  let single_file = if abs_path.is_dir() {
+    None
+  } else if abs_path.is_file() {
+    Some(opened_file_category(&abs_path)?)
+  } else {
+    return Err("路径不是文件或文件夹".to_string());
+  };
+  let _ = record_recent_path(&abs_path);
+
+  let label = path_label(&abs_path);
+  let started = ScanStarted {
+    scan_id: scan_id.clone(),
+    root: abs_path.to_string_lossy().into_owned(),
+    label: label.clone(),
+  };
+
+  let cancel_flag = registry.register(&scan_id);
+  let worker_app = app.clone();
+  let worker_window = window.label().to_string();
+  let worker_scan_id = scan_id.clone();
+  let spawned = std::thread::Builder::new()
+    .name("rustreader-scan".to_string())
+    .spawn(move || {
+      let app = worker_app;
+      let window = worker_window;
+      let scan_id = worker_scan_id;
+      let root = abs_path.to_string_lossy().into_owned();
+
+      let outcome = match single_file {
+        Some(category) => {
+          app.state::<FolderWatchState>().stop(&window);
+          let files = vec![single_scan_file(&abs_path, category)];
+          emit_scan_batch(&app, &window, &scan_id, files);
+          Some(ScanOutcome {
+            counts: ScanCounts {
+              scanned_dirs: 0,
+              scanned_files: 1,
+              matched_files: 1,
+              skipped: 0,
+            },
+            truncated: None,
+            warnings: Vec::new(),
+          })
+        }
+        None => {
+          stream_supported_files(&app, &window, &scan_id, &abs_path, &options, &cancel_flag)
+        }
+      };
+      app.state::<ScanRegistry>().unregister(&scan_id);
+
+      let (stage, outcome) = match outcome {
+        Some(outcome) => ("done", outcome),
+        None => ("cancelled", ScanOutcome::default()),
+      };
+      let counts = outcome.counts;
+      emit_scan_finished(
+        &app,
+        &window,
+        ScanFinishedEvent {
+          scan_id,
+          stage,
+          root,
+          label,
+          scanned_dirs: counts.scanned_dirs,
+          scanned_files: counts.scanned_files,
+          matched_files: counts.matched_files,
+          skipped: counts.skipped,
+          truncated: outcome.truncated.is_some(),
+          truncated_reason: outcome.truncated,
+          warnings: outcome.warnings,
+        },
+      );
+    });
+
+  if let Err(error) = spawned {
+    registry.unregister(&scan_id);
+    return Err(format!("启动扫描线程失败: {}", error));
+  }
+
+  Ok(Some(started))
+}
+
+#[tauri::command(rename_all = "snake_case")]
+fn cancel_scan(registry: tauri::State<'_, ScanRegistry>, scan_id: String) -> bool {
+  registry.cancel(scan_id.trim())
+}
+
+#[tauri::command(rename_all = "snake_case")]
+fn search_content(
+  app: tauri::AppHandle,
+  window: tauri::WebviewWindow,
+  root: String,
+  query: String,
+  search_id: String,
+  options: Option<SearchOptions>,
+  roots: Option<Vec<String>>,
+) -> Result<Option<SearchStarted>, String> {
+  if query.is_empty() {
+    return Ok(None);
+  }
+  let search_id = search_id.trim().to_string();
+  if search_id.is_empty() {
+    return Err("缺少搜索 ID".to_string());
+  }
+  let options = options.unwrap_or_default();
+  let matcher = ContentMatcher::new(&query, &options)?;
+  let max_matches = options.max_matches.unwrap_or(search::DEFAULT_MAX_MATCHES);
+
+  // The roots of a workspace replace `root`, and are searched under the names
+  // `scan_workspace` gave them.
+  let paths = match roots {
+    Some(roots) if !roots.is_empty() => roots,
+    _ => vec![root],
+  };
+  let mut abs_paths = Vec::with_capacity(paths.len());
+  for path in &paths {
+    let raw = normalize_file_url_to_path(path.trim());
+    let abs_path = PathBuf::from(raw.as_ref())
+      .canonicalize()
+      .map_err(|error| format!("路径不存在或无法访问: {}", error))?;
+    abs_paths.push(abs_path);
+  }
+  let roots = workspace::workspace_roots(&abs_paths);
+  let named = roots.len() > 1;
+  let mut targets = Vec::with_capacity(roots.len());
+  for root in roots {
+    let path = PathBuf::from(&root.path);
+    let single_file = if path.is_dir() {
+      None
+    } else if path.is_file() {
+      Some(opened_file_category(&path)?)
+    } else {
+      return Err("路径不是文件或文件夹".to_string());
+    };
+    targets.push(SearchTarget {
+      name: named.then_some(root.name),
+      path,
+      single_file,
+    });
+  }
+  let root = targets
+    .first()
+    .map(|target| target.path.to_string_lossy().into_owned())
+    .unwrap_or_default();
+  // The ignore, hidden and depth rules the tree was scanned with, so every match can be opened
+  // from the tree, but without the scan's limits.
+  let tree_options = app.state::<TreeScanOptions>().get(window.label());
+  let scan_options = ScanOptions {
+    max_files: None,
+    max_duration_ms: None,
+    collect_metadata: false,
+    ..tree_options.unwrap_or_else(|| resolve_scan_options(None))
+  };
+
+  let started = SearchStarted {
+    search_id: search_id.clone(),
+    root: root.clone(),
+  };
+
+  let registry = app.state::<ScanRegistry>();
+  let stop_flag = registry.register(&search_id);
+  let worker_app = app.clone();
+  let worker_window = window.label().to_string();
+  let worker_search_id = search_id.clone();
+  let spawned = std::thread::Builder::new()
+    .name("rustreader-search".to_string())
+    .spawn(move || {
+      let app = worker_app;
+      let window = worker_window;
+      let search_id = worker_search_id;
+      let sink =
+        ContentSearchSink::new(&app, &window, &search_id, &matcher, &stop_flag, max_matches);
+      let threads = scan::default_scan_threads();
+
+      let mut finished = true;
+      for target in &targets {
+        if stop_flag.load(Ordering::Relaxed) {
+          finished = false;
+          break;
+        }
+        let path = &target.path;
+        let walked = match (target.single_file, &target.name) {
+          (Some(category), name) => {
+            let virtual_path = name.clone().unwrap_or_else(|| path_label(path));
+            let abs_path = path.to_string_lossy().into_owned();
+            sink.files(vec![ScanFile::new(virtual_path, abs_path, category)]);
+            true
+          }
+          (None, Some(name)) => {
+            let prefixed = PrefixedSink::new(name, &sink);
+            scan::walk_supported_files(path, &scan_options, threads, &stop_flag, &prefixed)
+              .is_some()
+          }
+          (None, None) => {
+            scan::walk_supported_files(path, &scan_options, threads, &stop_flag, &sink).is_some()
+          }
+        };
+        if !walked {
+          finished = false;
+          break;
+        }
+      }
+      app.state::<ScanRegistry>().unregister(&search_id);
+
+      // Reaching `max_matches` also raises the flag, which is not a cancellation.
+      let cancelled = !finished && !sink.truncated.load(Ordering::Relaxed);
+      sink.finish(if cancelled { "cancelled" } else { "done" }, root);
+    });
+
+  if let Err(error) = spawned {
+    registry.unregister(&search_id);
+    return Err(format!("启动搜索线程失败: {}", error));
+  }
+
+  Ok(Some(started))
+}
+
+#[tauri::command(rename_all = "snake_case")]
+fn cancel_search(registry: tauri::State<'_, ScanRegistry>, search_id: String) -> bool {
+  registry.cancel(search_id.trim())
+}
+
+#[tauri::command(async, rename_all = "snake_case")]
+fn search_index(
+  fulltext: tauri::State<'_, FullTextState>,
+  root: String,
+  query: String,
+  limit: Option<usize>,
+) -> Result<IndexSearchResult, String> {
+  let raw = normalize_file_url_to_path(root.trim());
+  let abs_path = PathBuf::from(raw.as_ref())
+    .canonicalize()
+    .map_err(|error| format!("路径不存在或无法访问: {}", error))?;
+  fulltext.search(&abs_path, &query, limit.unwrap_or(fulltext::DEFAULT_SEARCH_LIMIT))
+}
+
+#[tauri::command(async)]
+fn extract_text(path: String) -> Result<ExtractedText, String> {
+  let raw = normalize_file_url_to_path(path.trim());
+  let abs_path = PathBuf::from(raw.as_ref())
+    .canonicalize()
+    .map_err(|error| format!("路径不存在或无法访问: {}", error))?;
+  if !abs_path.is_file() {
+    return Err("路径不是文件".to_string());
+  }
+  let category = opened_file_category(&abs_path)?;
+  let text = search::searchable_text(&abs_path, category)
+    .map_err(|error| format!("读取文件失败: {}", error))?
+    .ok_or_else(|| "无法从该文件提取文本".to_string())?;
+  Ok(ExtractedText { category, text })
+}
+
+#[tauri::command]
+fn stop_folder_watch(
+  window: tauri::WebviewWindow,
+  watch_state: tauri::State<'_, FolderWatchState>,
+) {
+  watch_state.stop(window.label());
+}
+
+#[tauri::command]
+fn watch_file(
+  app: tauri::AppHandle,
+  window: tauri::WebviewWindow,
+  watch_state: tauri::State<'_, FileWatchState>,
+  path: String,
+) -> Result<(), String> {
+  let raw = normalize_file_url_to_path(path.trim());
+  let abs_path = PathBuf::from(raw.as_ref())
+    .canonicalize()
+    .map_err(|error| format!("路径不存在或无法访问: {}", error))?;
+  if !abs_path.is_file() {
+    return Err("路径不是文件".to_string());
+  }
+  watch_state.watch(&app, window.label(), &abs_path, path)
+}
+
+#[tauri::command]
+fn unwatch_file(window: tauri::WebviewWindow, watch_state: tauri::State<'_, FileWatchState>) {
+  watch_state.stop(window.label());
 }
 
 #[tauri::command]
@@ -674,7 +1720,10 @@ fn load_app_config() -> Result<AppConfig, String> {
 }
 
 #[tauri::command]
-fn save_app_config(config: AppConfig) -> Result<(), String> {
+fn save_app_config(
+  fulltext: tauri::State<'_, FullTextState>,
+  config: AppConfig,
+) -> Result<(), String> {
   let mut merged = load_config_from_disk().unwrap_or_default();
   if config.language.is_some() {
     merged.language = config.language;
@@ -682,7 +1731,24 @@ fn save_app_config(config: AppConfig) -> Result<(), String> {
   if config.font_size_px.is_some() {
     merged.font_size_px = config.font_size_px;
   }
-  save_config_to_disk(&merged)
+  if config.scan_options.is_some() {
+    merged.scan_options = config.scan_options;
+  }
+  if config.file_types.is_some() {
+    merged.file_types = config.file_types;
+  }
+  if config.full_text.is_some() {
+    merged.full_text = config.full_text;
+  }
+  save_config_to_disk(&merged)?;
+  file_types::install(merged.file_types.as_deref().unwrap_or_default());
+  fulltext.set_options(merged.full_text.unwrap_or_default());
+  Ok(())
+}
+
+#[tauri::command]
+fn get_file_types() -> FileTypesInfo {
+  file_types::current().info()
 }
 
 #[tauri::command]
@@ -699,30 +1765,72 @@ fn get_recent_paths(limit: Option<u32>) -> Result<Vec<String>, String> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+  let open_args = cli_open_args();
+  let request = cli_open_request(&open_args);
+  let mut instance_lock = None;
+  // Logged once the logger is set up.
+  let mut instance_error = None;
+  if !open_args.new_window {
+    match instance_lock_path().and_then(|path| instance::claim(&path, &request)) {
+      Ok(Instance::Forwarded) => return,
+      Ok(Instance::Primary(lock)) => instance_lock = Some(lock),
+      // Without the lock the app still starts, it just cannot receive later invocations.
+      Err(error) => instance_error = Some(error),
+    }
+  }
+
+  let config = load_config_from_disk().unwrap_or_default();
+  file_types::install(config.file_types.as_deref().unwrap_or_default());
+  let fulltext = FullTextState::new(fulltext_index_dir().ok());
+  fulltext.set_options(config.full_text.unwrap_or_default());
+
+  let main_title = request.site_name.as_deref().map(build_window_title);
+  let window_targets = WindowTargets::default();
+  window_targets.insert(MAIN_WINDOW_LABEL, request);
+
   tauri::Builder::default()
+    .manage(ScanRegistry::default())
+    .manage(FolderWatchState::default())
+    .manage(FileWatchState::default())
+    .manage(TreeScanOptions::default())
+    .manage(fulltext)
+    .manage(window_targets)
     .invoke_handler(tauri::generate_handler![
       get_cli_open_target,
+      get_cli_open_targets,
+      get_cli_selection,
       get_cli_site_name,
       set_app_window_title,
+      open_in_new_window,
       load_app_config,
       save_app_config,
       get_recent_paths,
+      get_file_types,
       scan_path,
+      scan_workspace,
       pick_and_scan_file,
-      pick_and_scan_folder
+      pick_and_scan_folder,
+      start_scan,
+      cancel_scan,
+      search_content,
+      cancel_search,
+      search_index,
+      extract_text,
+      stop_folder_watch,
+      watch_file,
+      unwatch_file
     ])
-    .setup(|app| {
-      if let Some(site_name) = parse_cli_site_name(std::env::args_os().skip(1)) {
-        let site_name = site_name.trim();
-        if !site_name.is_empty() {
-          let title = build_window_title(site_name);
-          for window in app.webview_windows().values() {
-            let _ = window.set_title(&title);
-          }
+    .setup(move |app| {
+      if let Some(lock) = instance_lock.take() {
+        if let Err(error) = instance::serve(app.handle(), lock) {
+          log::warn!("{}", error);
         }
       }
 
-      for window in app.webview_windows().values() {
+      if let Some(window) = app.get_webview_window(MAIN_WINDOW_LABEL) {
+        if let Some(title) = &main_title {
+          let _ = window.set_title(title);
+        }
         let _ = window.maximize();
       }
 
@@ -733,8 +1841,16 @@ pub fn run() {
             .build(),
         )?;
       }
+      if let Some(error) = instance_error.take() {
+        log::warn!("{}", error);
+      }
       Ok(())
     })
-    .run(tauri::generate_context!())
+    .on_window_event(|window, event| {
+      if let tauri::WindowEvent::Destroyed = event {
+        forget_window(window.app_handle(), window.label());
+      }
+    })
+    .run(app_context())
     .expect("error while running tauri application");
 }
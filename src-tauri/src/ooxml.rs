@@ -0,0 +1,77 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// `[Content_Types].xml` is a few kilobytes; anything far larger is not a real manifest.
+const MAX_CONTENT_TYPES_LEN: u64 = 1024 * 1024;
+
+/// Main-part content types, without the `application/` prefix and `.main+xml` suffix.
+/// Covers documents, templates, slide shows and their macro-enabled variants.
+const MAIN_CONTENT_TYPES: &[(&str, &str)] = &[
+  ("vnd.openxmlformats-officedocument.wordprocessingml.document", "word"),
+  ("vnd.openxmlformats-officedocument.wordprocessingml.template", "word"),
+  ("vnd.ms-word.document.macroenabled", "word"),
+  ("vnd.ms-word.template.macroenabledtemplate", "word"),
+  ("vnd.openxmlformats-officedocument.spreadsheetml.sheet", "excel"),
+  ("vnd.openxmlformats-officedocument.spreadsheetml.template", "excel"),
+  ("vnd.ms-excel.sheet.macroenabled", "excel"),
+  ("vnd.ms-excel.template.macroenabled", "excel"),
+  ("vnd.openxmlformats-officedocument.presentationml.presentation", "slides"),
+  ("vnd.openxmlformats-officedocument.presentationml.template", "slides"),
+  ("vnd.openxmlformats-officedocument.presentationml.slideshow", "slides"),
+  ("vnd.ms-powerpoint.presentation.macroenabled", "slides"),
+  ("vnd.ms-powerpoint.template.macroenabled", "slides"),
+  ("vnd.ms-powerpoint.slideshow.macroenabled", "slides"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct OoxmlInfo {
+  pub(crate) category: &'static str,
+  pub(crate) has_macros: bool,
+}
+
+pub(crate) fn is_office_category(category: &str) -> bool {
+  matches!(category, "word" | "excel" | "slides")
+}
+
+/// Reads the package manifest of a Word, Excel or PowerPoint file.
+///
+/// Returns `Ok(None)` for archives that are not Office Open XML packages.
+pub(crate) fn inspect(path: &Path) -> std::io::Result<Option<OoxmlInfo>> {
+  let file = File::open(path)?;
+  let Ok(mut archive) = zip::ZipArchive::new(file) else {
+    return Ok(None);
+  };
+
+  let content_types = {
+    let Ok(entry) = archive.by_name("[Content_Types].xml") else {
+      return Ok(None);
+    };
+    let mut content_types = String::new();
+    entry
+      .take(MAX_CONTENT_TYPES_LEN)
+      .read_to_string(&mut content_types)?;
+    content_types.to_ascii_lowercase()
+  };
+
+  let Some(category) = main_part_category(&content_types) else {
+    return Ok(None);
+  };
+  // A VBA project part is what actually carries macros; a `.docm` may have none left.
+  let has_macros = content_types.contains("application/vnd.ms-office.vbaproject")
+    || archive
+      .file_names()
+      .any(|name| name.to_ascii_lowercase().ends_with("vbaproject.bin"));
+
+  Ok(Some(OoxmlInfo {
+    category,
+    has_macros,
+  }))
+}
+
+fn main_part_category(content_types: &str) -> Option<&'static str> {
+  MAIN_CONTENT_TYPES.iter().find_map(|(content_type, category)| {
+    let needle = format!("\"application/{}.main+xml\"", content_type);
+    content_types.contains(&needle).then_some(*category)
+  })
+}
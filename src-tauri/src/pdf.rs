@@ -0,0 +1,957 @@
+use flate2::read::ZlibDecoder;
+use regex::bytes::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+/// Larger PDFs are mostly scans; reading them whole for a few lines of text is not worth it.
+const MAX_PDF_LEN: u64 = 128 * 1024 * 1024;
+const MAX_DECODED_STREAM_LEN: u64 = 64 * 1024 * 1024;
+/// Page trees and reference chains deeper than this are treated as broken.
+const MAX_NESTING: usize = 64;
+/// A `TJ` adjustment this far left (in thousandths of an em) is a word gap, not kerning.
+const TJ_WORD_GAP: f64 = -200.0;
+
+type Dict = HashMap<Vec<u8>, Object>;
+
+#[derive(Debug, Clone)]
+enum Object {
+  Null,
+  Number(f64),
+  Name(Vec<u8>),
+  String(Vec<u8>),
+  Array(Vec<Object>),
+  Dict(Dict),
+  Ref(u32),
+  /// A bare keyword: a content-stream operator, or `obj`, `stream`, `R` and friends.
+  Keyword(Vec<u8>),
+}
+
+impl Object {
+  fn as_dict(&self) -> Option<&Dict> {
+    match self {
+      Object::Dict(dict) => Some(dict),
+      _ => None,
+    }
+  }
+
+  fn as_name(&self) -> Option<&[u8]> {
+    match self {
+      Object::Name(name) => Some(name),
+      _ => None,
+    }
+  }
+
+  fn as_number(&self) -> Option<f64> {
+    match self {
+      Object::Number(number) => Some(*number),
+      _ => None,
+    }
+  }
+}
+
+/// Plain text of the PDF at `path`, page by page. Returns `Ok(None)` for files that are not
+/// PDFs, are encrypted, or are too large; pages whose fonts cannot be mapped to Unicode come
+/// out empty.
+pub(crate) fn extract_text(path: &Path) -> std::io::Result<Option<String>> {
+  let file = File::open(path)?;
+  if file.metadata()?.len() > MAX_PDF_LEN {
+    return Ok(None);
+  }
+  let mut data = Vec::new();
+  file.take(MAX_PDF_LEN).read_to_end(&mut data)?;
+  if !data.starts_with(b"%PDF-") || contains(&data, b"/Encrypt") {
+    return Ok(None);
+  }
+  Ok(Some(Document::parse(&data).text()))
+}
+
+/// The objects of a PDF, found by scanning for `n g obj` rather than trusting the xref table,
+/// so files with broken or missing cross-references still read.
+struct Document {
+  objects: HashMap<u32, Object>,
+  streams: HashMap<u32, Vec<u8>>,
+}
+
+impl Document {
+  fn parse(data: &[u8]) -> Self {
+    static OBJECT_HEADER: OnceLock<Regex> = OnceLock::new();
+    let header = OBJECT_HEADER.get_or_init(|| {
+      Regex::new(r"(?-u)(\d+)[\x00\t\n\x0c\r ]+\d+[\x00\t\n\x0c\r ]+obj\b").expect("valid regex")
+    });
+
+    let mut document = Document {
+      objects: HashMap::new(),
+      streams: HashMap::new(),
+    };
+    let mut resume_at = 0;
+    for captures in header.captures_iter(data) {
+      let whole = captures.get(0).expect("match");
+      // Compressed stream data can contain anything, including text that looks like a header.
+      if whole.start() < resume_at {
+        continue;
+      }
+      let Some(id) = std::str::from_utf8(&captures[1]).ok().and_then(|id| id.parse().ok()) else {
+        continue;
+      };
+      let mut lexer = Lexer::new(data, whole.end());
+      let Some(object) = lexer.next_object() else {
+        continue;
+      };
+      if let Some(raw) = lexer.stream_data(&object) {
+        resume_at = lexer.pos;
+        document.streams.insert(id, raw.to_vec());
+      }
+      // Later definitions come from incremental updates and replace earlier ones.
+      document.objects.insert(id, object);
+    }
+    document.expand_object_streams();
+    document
+  }
+
+  /// Adds the objects packed into `/Type /ObjStm` streams (PDF 1.5 compression).
+  fn expand_object_streams(&mut self) {
+    let object_streams: Vec<u32> = self
+      .objects
+      .iter()
+      .filter(|(_, object)| dict_type(object) == Some(b"ObjStm"))
+      .map(|(id, _)| *id)
+      .collect();
+    for stream_id in object_streams {
+      let Some(data) = self.decoded_stream(stream_id) else {
+        continue;
+      };
+      let Some(dict) = self.objects.get(&stream_id).and_then(Object::as_dict) else {
+        continue;
+      };
+      let count = dict.get(&b"N"[..]).and_then(Object::as_number).unwrap_or(0.0) as usize;
+      let first = dict.get(&b"First"[..]).and_then(Object::as_number).unwrap_or(0.0) as usize;
+
+      let mut header = Lexer::new(&data, 0);
+      // Each entry takes at least four bytes, so a bogus `/N` cannot reserve much.
+      let mut entries = Vec::with_capacity(count.min(data.len() / 4));
+      for _ in 0..count {
+        let id = header.next_object().and_then(|object| object.as_number());
+        let offset = header.next_object().and_then(|object| object.as_number());
+        match (id, offset) {
+          (Some(id), Some(offset)) => entries.push((id as u32, first.checked_add(offset as usize))),
+          _ => break,
+        }
+      }
+      for (id, offset) in entries {
+        let Some(offset) = offset.filter(|offset| *offset < data.len()) else {
+          continue;
+        };
+        if let Some(object) = Lexer::new(&data, offset).next_object() {
+          self.objects.entry(id).or_insert(object);
+        }
+      }
+    }
+  }
+
+  fn resolve<'a>(&'a self, mut object: &'a Object) -> &'a Object {
+    for _ in 0..MAX_NESTING {
+      match object {
+        Object::Ref(id) => match self.objects.get(id) {
+          Some(target) => object = target,
+          None => return &Object::Null,
+        },
+        _ => return object,
+      }
+    }
+    &Object::Null
+  }
+
+  fn get<'a>(&'a self, dict: &'a Dict, key: &[u8]) -> &'a Object {
+    dict.get(key).map_or(&Object::Null, |object| self.resolve(object))
+  }
+
+  fn decoded_stream(&self, id: u32) -> Option<Vec<u8>> {
+    let raw = self.streams.get(&id)?;
+    let dict = self.objects.get(&id)?.as_dict()?;
+    let filters = match self.get(dict, b"Filter") {
+      Object::Name(name) => vec![name.clone()],
+      Object::Array(items) => items
+        .iter()
+        .filter_map(|item| self.resolve(item).as_name().map(<[u8]>::to_vec))
+        .collect(),
+      _ => Vec::new(),
+    };
+    let mut data = raw.clone();
+    for filter in filters {
+      match filter.as_slice() {
+        b"FlateDecode" | b"Fl" => data = inflate(&data)?,
+        // Image and legacy filters never carry text worth extracting.
+        _ => return None,
+      }
+    }
+    Some(data)
+  }
+
+  /// Page object ids in reading order, following the page tree from the catalog. Files whose
+  /// tree cannot be followed fall back to every page object in object-number order.
+  fn pages(&self) -> Vec<(u32, Option<&Object>)> {
+    let mut pages = Vec::new();
+    let root = self
+      .objects
+      .values()
+      .find(|object| dict_type(object) == Some(b"Catalog"))
+      .and_then(Object::as_dict)
+      .and_then(|catalog| catalog.get(&b"Pages"[..]));
+    if let Some(Object::Ref(root)) = root {
+      let mut visited = HashSet::new();
+      self.collect_pages(*root, None, &mut visited, &mut pages, 0);
+    }
+    if pages.is_empty() {
+      let mut ids: Vec<u32> = self
+        .objects
+        .iter()
+        .filter(|(_, object)| dict_type(object) == Some(b"Page"))
+        .map(|(id, _)| *id)
+        .collect();
+      ids.sort_unstable();
+      pages = ids.into_iter().map(|id| (id, None)).collect();
+    }
+    pages
+  }
+
+  fn collect_pages<'a>(
+    &'a self,
+    id: u32,
+    inherited: Option<&'a Object>,
+    visited: &mut HashSet<u32>,
+    pages: &mut Vec<(u32, Option<&'a Object>)>,
+    depth: usize,
+  ) {
+    if depth > MAX_NESTING || !visited.insert(id) {
+      return;
+    }
+    let Some(dict) = self.objects.get(&id).and_then(Object::as_dict) else {
+      return;
+    };
+    let resources = dict.get(&b"Resources"[..]).or(inherited);
+    match dict_type_of(dict) {
+      Some(b"Page") => pages.push((id, resources)),
+      _ => {
+        if let Object::Array(kids) = self.get(dict, b"Kids") {
+          for kid in kids {
+            if let Object::Ref(kid) = kid {
+              self.collect_pages(*kid, resources, visited, pages, depth + 1);
+            }
+          }
+        }
+      }
+    }
+  }
+
+  fn text(&self) -> String {
+    let mut text = String::new();
+    let mut decoders: HashMap<u32, Rc<FontDecoder>> = HashMap::new();
+    for (page_id, resources) in self.pages() {
+      let Some(page) = self.objects.get(&page_id).and_then(Object::as_dict) else {
+        continue;
+      };
+      let resources = resources
+        .map(|resources| self.resolve(resources))
+        .or_else(|| page.get(&b"Resources"[..]).map(|resources| self.resolve(resources)));
+      let fonts = self.page_fonts(resources, &mut decoders);
+
+      let mut content = Vec::new();
+      let streams = match page.get(&b"Contents"[..]) {
+        Some(Object::Ref(id)) => match self.objects.get(id) {
+          Some(Object::Array(items)) => items.clone(),
+          _ => vec![Object::Ref(*id)],
+        },
+        Some(Object::Array(items)) => items.clone(),
+        _ => Vec::new(),
+      };
+      for stream in streams {
+        if let Object::Ref(id) = stream {
+          if let Some(data) = self.decoded_stream(id) {
+            content.extend_from_slice(&data);
+            content.push(b'\n');
+          }
+        }
+      }
+
+      let page_text = content_text(&content, &fonts);
+      let page_text = page_text.trim();
+      if !page_text.is_empty() {
+        text.push_str(page_text);
+        text.push_str("\n\n");
+      }
+    }
+    text
+  }
+
+  /// Resource names of the page's fonts, each with a decoder shared across pages.
+  fn page_fonts(
+    &self,
+    resources: Option<&Object>,
+    decoders: &mut HashMap<u32, Rc<FontDecoder>>,
+  ) -> HashMap<Vec<u8>, Rc<FontDecoder>> {
+    let mut fonts = HashMap::new();
+    let Some(resources) = resources.and_then(Object::as_dict) else {
+      return fonts;
+    };
+    let Some(font_dict) = self.get(resources, b"Font").as_dict() else {
+      return fonts;
+    };
+    for (name, font) in font_dict {
+      let decoder = match font {
+        Object::Ref(id) => decoders
+          .entry(*id)
+          .or_insert_with(|| Rc::new(self.font_decoder(self.resolve(font))))
+          .clone(),
+        _ => Rc::new(self.font_decoder(font)),
+      };
+      fonts.insert(name.clone(), decoder);
+    }
+    fonts
+  }
+
+  fn font_decoder(&self, font: &Object) -> FontDecoder {
+    let Some(dict) = font.as_dict() else {
+      return FontDecoder::default();
+    };
+    let composite = self.get(dict, b"Subtype").as_name() == Some(b"Type0");
+    let cmap = match dict.get(&b"ToUnicode"[..]) {
+      Some(Object::Ref(id)) => self.decoded_stream(*id).map(|data| CMap::parse(&data)),
+      _ => None,
+    };
+    FontDecoder { cmap, composite }
+  }
+}
+
+fn dict_type(object: &Object) -> Option<&[u8]> {
+  object.as_dict().and_then(dict_type_of)
+}
+
+fn dict_type_of(dict: &Dict) -> Option<&[u8]> {
+  dict.get(&b"Type"[..]).and_then(Object::as_name)
+}
+
+/// Interprets the text operators of a content stream, ignoring graphics and positioning
+/// except for line changes.
+fn content_text(content: &[u8], fonts: &HashMap<Vec<u8>, Rc<FontDecoder>>) -> String {
+  let fallback = FontDecoder::default();
+  let mut text = String::new();
+  let mut font: &FontDecoder = &fallback;
+  let mut operands: Vec<Object> = Vec::new();
+  let mut line_y: Option<f64> = None;
+  let mut lexer = Lexer::new(content, 0);
+
+  while let Some(object) = lexer.next_object() {
+    let Object::Keyword(operator) = object else {
+      operands.push(object);
+      continue;
+    };
+    match operator.as_slice() {
+      b"Tf" => {
+        font = operands
+          .first()
+          .and_then(Object::as_name)
+          .and_then(|name| fonts.get(name))
+          .map_or(&fallback, |decoder| decoder.as_ref());
+      }
+      b"Tj" => {
+        if let Some(Object::String(bytes)) = operands.last() {
+          text.push_str(&font.decode(bytes));
+        }
+      }
+      b"'" | b"\"" => {
+        push_line_break(&mut text);
+        if let Some(Object::String(bytes)) = operands.last() {
+          text.push_str(&font.decode(bytes));
+        }
+      }
+      b"TJ" => {
+        if let Some(Object::Array(items)) = operands.last() {
+          for item in items {
+            match item {
+              Object::String(bytes) => text.push_str(&font.decode(bytes)),
+              Object::Number(adjust) if *adjust < TJ_WORD_GAP => push_space(&mut text),
+              _ => {}
+            }
+          }
+        }
+      }
+      b"T*" => push_line_break(&mut text),
+      b"Td" | b"TD" => {
+        let moved_down = operands.get(1).and_then(Object::as_number).is_some_and(|ty| ty != 0.0);
+        if moved_down {
+          push_line_break(&mut text);
+        }
+      }
+      b"Tm" => {
+        let y = operands.get(5).and_then(Object::as_number);
+        if line_y.is_some() && y != line_y {
+          push_line_break(&mut text);
+        }
+        line_y = y;
+      }
+      b"ET" => push_space(&mut text),
+      b"BI" => lexer.skip_inline_image(),
+      _ => {}
+    }
+    operands.clear();
+  }
+  text
+}
+
+fn push_space(text: &mut String) {
+  if !text.is_empty() && !text.ends_with(char::is_whitespace) {
+    text.push(' ');
+  }
+}
+
+fn push_line_break(text: &mut String) {
+  let trimmed = text.trim_end_matches(' ').len();
+  text.truncate(trimmed);
+  if !text.is_empty() && !text.ends_with('\n') {
+    text.push('\n');
+  }
+}
+
+#[derive(Default)]
+struct FontDecoder {
+  cmap: Option<CMap>,
+  /// Type0 fonts use multi-byte codes that mean nothing without a `ToUnicode` map.
+  composite: bool,
+}
+
+impl FontDecoder {
+  fn decode(&self, bytes: &[u8]) -> String {
+    match &self.cmap {
+      Some(cmap) => cmap.decode(bytes),
+      None if self.composite => String::new(),
+      // Simple fonts without a map: close enough to PDFDocEncoding for Latin text.
+      None => bytes
+        .iter()
+        .filter(|byte| **byte >= 0x20 || **byte == b'\t')
+        .map(|byte| char::from(*byte))
+        .collect(),
+    }
+  }
+}
+
+enum RangeTarget {
+  /// Consecutive codes map to consecutive characters starting here (as UTF-16).
+  Start(Vec<u16>),
+  List(Vec<String>),
+}
+
+/// A `ToUnicode` CMap: byte codes to the text they represent.
+#[derive(Default)]
+struct CMap {
+  /// `(code length, low, high)`.
+  codespaces: Vec<(usize, u32, u32)>,
+  chars: HashMap<(usize, u32), String>,
+  ranges: Vec<(usize, u32, u32, RangeTarget)>,
+}
+
+impl CMap {
+  fn parse(data: &[u8]) -> Self {
+    let mut cmap = CMap::default();
+    let mut lexer = Lexer::new(data, 0);
+    while let Some(object) = lexer.next_object() {
+      let Object::Keyword(keyword) = object else {
+        continue;
+      };
+      match keyword.as_slice() {
+        b"begincodespacerange" => {
+          for operands in section(&mut lexer, b"endcodespacerange", 2) {
+            // An empty range would match without consuming any bytes.
+            if let [Object::String(low), Object::String(high)] = operands.as_slice() {
+              if !low.is_empty() {
+                cmap.codespaces.push((low.len(), code(low), code(high)));
+              }
+            }
+          }
+        }
+        b"beginbfchar" => {
+          for operands in section(&mut lexer, b"endbfchar", 2) {
+            if let [Object::String(source), Object::String(target)] = operands.as_slice() {
+              cmap.chars.insert((source.len(), code(source)), utf16_text(target));
+            }
+          }
+        }
+        b"beginbfrange" => {
+          for operands in section(&mut lexer, b"endbfrange", 3) {
+            let [Object::String(low), Object::String(high), target] = operands.as_slice() else {
+              continue;
+            };
+            let target = match target {
+              Object::String(start) => RangeTarget::Start(utf16_units(start)),
+              Object::Array(items) => RangeTarget::List(
+                items
+                  .iter()
+                  .map(|item| match item {
+                    Object::String(bytes) => utf16_text(bytes),
+                    _ => String::new(),
+                  })
+                  .collect(),
+              ),
+              _ => continue,
+            };
+            cmap.ranges.push((low.len(), code(low), code(high), target));
+          }
+        }
+        _ => {}
+      }
+    }
+    cmap
+  }
+
+  fn decode(&self, bytes: &[u8]) -> String {
+    let shortest = self.codespaces.iter().map(|(length, _, _)| *length).min().unwrap_or(1);
+    let mut text = String::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+      let length = self
+        .codespaces
+        .iter()
+        .find(|(length, low, high)| {
+          pos + length <= bytes.len() && (*low..=*high).contains(&code(&bytes[pos..pos + length]))
+        })
+        .map_or(shortest, |(length, _, _)| *length)
+        .clamp(1, bytes.len() - pos);
+      if let Some(mapped) = self.lookup(length, code(&bytes[pos..pos + length])) {
+        text.push_str(&mapped);
+      }
+      pos += length;
+    }
+    text
+  }
+
+  fn lookup(&self, length: usize, value: u32) -> Option<String> {
+    if let Some(mapped) = self.chars.get(&(length, value)) {
+      return Some(mapped.clone());
+    }
+    self.ranges.iter().find_map(|(range_length, low, high, target)| {
+      if *range_length != length || value < *low || value > *high {
+        return None;
+      }
+      let offset = value - low;
+      match target {
+        RangeTarget::Start(start) => {
+          let mut units = start.clone();
+          let last = units.last_mut()?;
+          *last = last.wrapping_add(offset as u16);
+          Some(String::from_utf16_lossy(&units))
+        }
+        RangeTarget::List(items) => items.get(offset as usize).cloned(),
+      }
+    })
+  }
+}
+
+/// Operand groups of `width` objects up to the `end` keyword of a CMap section.
+fn section(lexer: &mut Lexer, end: &[u8], width: usize) -> Vec<Vec<Object>> {
+  let mut groups = Vec::new();
+  let mut group = Vec::with_capacity(width);
+  while let Some(object) = lexer.next_object() {
+    if matches!(&object, Object::Keyword(keyword) if keyword == end) {
+      break;
+    }
+    group.push(object);
+    if group.len() == width {
+      groups.push(std::mem::replace(&mut group, Vec::with_capacity(width)));
+    }
+  }
+  groups
+}
+
+fn code(bytes: &[u8]) -> u32 {
+  bytes.iter().take(4).fold(0, |value, byte| (value << 8) | u32::from(*byte))
+}
+
+fn utf16_units(bytes: &[u8]) -> Vec<u16> {
+  bytes
+    .chunks(2)
+    .map(|pair| match pair {
+      [high, low] => u16::from_be_bytes([*high, *low]),
+      [single] => u16::from(*single),
+      _ => 0,
+    })
+    .collect()
+}
+
+fn utf16_text(bytes: &[u8]) -> String {
+  String::from_utf16_lossy(&utf16_units(bytes))
+}
+
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+  let mut decoded = Vec::new();
+  let result = ZlibDecoder::new(data)
+    .take(MAX_DECODED_STREAM_LEN)
+    .read_to_end(&mut decoded);
+  // Truncated or slightly corrupt streams still yield their leading text.
+  match result {
+    Ok(_) => Some(decoded),
+    Err(_) if !decoded.is_empty() => Some(decoded),
+    Err(_) => None,
+  }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+  haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+fn is_whitespace(byte: u8) -> bool {
+  matches!(byte, b'\0' | b'\t' | b'\n' | 0x0c | b'\r' | b' ')
+}
+
+fn is_delimiter(byte: u8) -> bool {
+  matches!(byte, b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}' | b'/' | b'%')
+}
+
+/// Tokenizer for the PDF object syntax, shared by file bodies, content streams and CMaps.
+struct Lexer<'a> {
+  data: &'a [u8],
+  pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+  fn new(data: &'a [u8], pos: usize) -> Self {
+    Self { data, pos }
+  }
+
+  fn peek(&self) -> Option<u8> {
+    self.data.get(self.pos).copied()
+  }
+
+  fn skip_whitespace(&mut self) {
+    while let Some(byte) = self.peek() {
+      if is_whitespace(byte) {
+        self.pos += 1;
+      } else if byte == b'%' {
+        while self.peek().is_some_and(|byte| byte != b'\n' && byte != b'\r') {
+          self.pos += 1;
+        }
+      } else {
+        break;
+      }
+    }
+  }
+
+  fn next_object(&mut self) -> Option<Object> {
+    self.next_object_at_depth(0)
+  }
+
+  fn next_object_at_depth(&mut self, depth: usize) -> Option<Object> {
+    self.skip_whitespace();
+    let byte = self.peek()?;
+    if depth > MAX_NESTING {
+      self.pos = self.data.len();
+      return None;
+    }
+    match byte {
+      b'/' => {
+        self.pos += 1;
+        Some(Object::Name(self.read_name()))
+      }
+      b'(' => {
+        self.pos += 1;
+        Some(Object::String(self.read_literal_string()))
+      }
+      b'<' if self.data.get(self.pos + 1) == Some(&b'<') => {
+        self.pos += 2;
+        let mut dict = Dict::new();
+        loop {
+          self.skip_whitespace();
+          match self.peek() {
+            None => break,
+            Some(b'>') if self.data.get(self.pos + 1) == Some(&b'>') => {
+              self.pos += 2;
+              break;
+            }
+            Some(b'>') => {
+              // A lone `>`, as at the end of a truncated file: skip it.
+              self.pos += 1;
+              continue;
+            }
+            _ => {}
+          }
+          let Some(key) = self.next_object_at_depth(depth + 1) else {
+            break;
+          };
+          let Object::Name(key) = key else {
+            continue;
+          };
+          let value = self.next_object_at_depth(depth + 1).unwrap_or(Object::Null);
+          dict.insert(key, value);
+        }
+        Some(Object::Dict(dict))
+      }
+      b'<' => {
+        self.pos += 1;
+        Some(Object::String(self.read_hex_string()))
+      }
+      b'[' => {
+        self.pos += 1;
+        let mut items = Vec::new();
+        loop {
+          self.skip_whitespace();
+          match self.peek() {
+            None => break,
+            Some(b']') => {
+              self.pos += 1;
+              break;
+            }
+            _ => {}
+          }
+          match self.next_object_at_depth(depth + 1) {
+            Some(item) => items.push(item),
+            None => break,
+          }
+        }
+        Some(Object::Array(items))
+      }
+      b']' | b'>' | b')' | b'{' | b'}' => {
+        // Stray closing delimiter: skip it so malformed input cannot stall the lexer.
+        self.pos += 1;
+        Some(Object::Keyword(vec![byte]))
+      }
+      b'+' | b'-' | b'.' | b'0'..=b'9' => {
+        let number = self.read_number();
+        Some(self.try_reference(number).unwrap_or(Object::Number(number)))
+      }
+      _ => {
+        let keyword = self.read_regular();
+        Some(match keyword.as_slice() {
+          b"null" => Object::Null,
+          // Booleans never matter for text, so `true` and `false` stay keywords.
+          _ => Object::Keyword(keyword),
+        })
+      }
+    }
+  }
+
+  /// Turns `n g R` into a reference, leaving the position untouched otherwise.
+  fn try_reference(&mut self, number: f64) -> Option<Object> {
+    if number < 0.0 || number.fract() != 0.0 {
+      return None;
+    }
+    let start = self.pos;
+    self.skip_whitespace();
+    if self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+      self.read_number();
+      self.skip_whitespace();
+      let ends_keyword = |byte: &u8| is_whitespace(*byte) || is_delimiter(*byte);
+      if self.peek() == Some(b'R') && self.data.get(self.pos + 1).map_or(true, ends_keyword) {
+        self.pos += 1;
+        return Some(Object::Ref(number as u32));
+      }
+    }
+    self.pos = start;
+    None
+  }
+
+  /// A keyword; always consumes at least one byte so malformed input cannot stall the lexer.
+  fn read_regular(&mut self) -> Vec<u8> {
+    let keyword = self.read_regular_or_empty();
+    if keyword.is_empty() {
+      self.pos += 1;
+      return self.data[self.pos - 1..self.pos].to_vec();
+    }
+    keyword
+  }
+
+  fn read_number(&mut self) -> f64 {
+    let start = self.pos;
+    while self
+      .peek()
+      .is_some_and(|byte| matches!(byte, b'+' | b'-' | b'.' | b'0'..=b'9'))
+    {
+      self.pos += 1;
+    }
+    std::str::from_utf8(&self.data[start..self.pos])
+      .ok()
+      .and_then(|value| value.parse().ok())
+      .unwrap_or(0.0)
+  }
+
+  fn read_name(&mut self) -> Vec<u8> {
+    let raw = self.read_regular_or_empty();
+    let mut name = Vec::with_capacity(raw.len());
+    let mut index = 0;
+    while index < raw.len() {
+      // `#xx` escapes any byte, e.g. a space as `#20`.
+      let escaped = (raw[index] == b'#')
+        .then(|| raw.get(index + 1..index + 3))
+        .flatten()
+        .and_then(|hex| std::str::from_utf8(hex).ok())
+        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+      match escaped {
+        Some(byte) => {
+          name.push(byte);
+          index += 3;
+        }
+        None => {
+          name.push(raw[index]);
+          index += 1;
+        }
+      }
+    }
+    name
+  }
+
+  fn read_regular_or_empty(&mut self) -> Vec<u8> {
+    let start = self.pos;
+    while self.peek().is_some_and(|byte| !is_whitespace(byte) && !is_delimiter(byte)) {
+      self.pos += 1;
+    }
+    self.data[start..self.pos].to_vec()
+  }
+
+  fn read_literal_string(&mut self) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut nesting = 0usize;
+    while let Some(byte) = self.peek() {
+      self.pos += 1;
+      match byte {
+        b'(' => {
+          nesting += 1;
+          bytes.push(byte);
+        }
+        b')' if nesting == 0 => break,
+        b')' => {
+          nesting -= 1;
+          bytes.push(byte);
+        }
+        b'\\' => {
+          let Some(escaped) = self.peek() else {
+            break;
+          };
+          self.pos += 1;
+          match escaped {
+            b'n' => bytes.push(b'\n'),
+            b'r' => bytes.push(b'\r'),
+            b't' => bytes.push(b'\t'),
+            b'b' => bytes.push(0x08),
+            b'f' => bytes.push(0x0c),
+            b'0'..=b'7' => {
+              let mut value = u32::from(escaped - b'0');
+              for _ in 0..2 {
+                match self.peek() {
+                  Some(digit @ b'0'..=b'7') => {
+                    value = value * 8 + u32::from(digit - b'0');
+                    self.pos += 1;
+                  }
+                  _ => break,
+                }
+              }
+              bytes.push(value as u8);
+            }
+            // A backslash before a line break continues the string on the next line.
+            b'\r' => {
+              if self.peek() == Some(b'\n') {
+                self.pos += 1;
+              }
+            }
+            b'\n' => {}
+            other => bytes.push(other),
+          }
+        }
+        other => bytes.push(other),
+      }
+    }
+    bytes
+  }
+
+  fn read_hex_string(&mut self) -> Vec<u8> {
+    let mut digits = Vec::new();
+    while let Some(byte) = self.peek() {
+      self.pos += 1;
+      if byte == b'>' {
+        break;
+      }
+      if let Some(digit) = char::from(byte).to_digit(16) {
+        digits.push(digit as u8);
+      }
+    }
+    if digits.len() % 2 == 1 {
+      digits.push(0);
+    }
+    digits.chunks(2).map(|pair| (pair[0] << 4) | pair[1]).collect()
+  }
+
+  /// The raw bytes of the stream following `object`, when it is a stream dictionary. Leaves
+  /// the position after `endstream`.
+  fn stream_data(&mut self, object: &Object) -> Option<&'a [u8]> {
+    let dict = object.as_dict()?;
+    self.skip_whitespace();
+    if !self.data.get(self.pos..)?.starts_with(b"stream") {
+      return None;
+    }
+    self.pos += b"stream".len();
+    if self.data[self.pos..].starts_with(b"\r\n") {
+      self.pos += 2;
+    } else if self.peek() == Some(b'\n') || self.peek() == Some(b'\r') {
+      self.pos += 1;
+    }
+    let start = self.pos;
+
+    // Trust a direct `/Length` when `endstream` really follows it; indirect lengths and
+    // wrong values fall back to searching for the keyword.
+    let declared = dict
+      .get(&b"Length"[..])
+      .and_then(Object::as_number)
+      .and_then(|length| start.checked_add(length as usize))
+      .filter(|end| {
+        *end <= self.data.len() && {
+          let mut after = Lexer::new(self.data, *end);
+          after.skip_whitespace();
+          self.data[after.pos..].starts_with(b"endstream")
+        }
+      });
+    let end = match declared {
+      Some(end) => end,
+      None => {
+        let offset = self.data[start..]
+          .windows(b"endstream".len())
+          .position(|window| window == b"endstream")?;
+        let mut end = start + offset;
+        if self.data[..end].ends_with(b"\r\n") {
+          end -= 2;
+        } else if self.data[..end].ends_with(b"\n") || self.data[..end].ends_with(b"\r") {
+          end -= 1;
+        }
+        end.max(start)
+      }
+    };
+    self.pos = end;
+    if let Some(offset) = self.data[end..]
+      .windows(b"endstream".len())
+      .position(|window| window == b"endstream")
+    {
+      self.pos = end + offset + b"endstream".len();
+    }
+    Some(&self.data[start..end])
+  }
+
+  /// Skips the binary data of an inline image (`BI ... ID <data> EI`).
+  fn skip_inline_image(&mut self) {
+    let Some(offset) = self.data[self.pos..].windows(2).position(|window| window == b"ID") else {
+      self.pos = self.data.len();
+      return;
+    };
+    self.pos += offset + 2;
+    while self.pos + 2 <= self.data.len() {
+      let at_end = self.data[self.pos..].starts_with(b"EI")
+        && self.pos > 0
+        && is_whitespace(self.data[self.pos - 1])
+        && self.data.get(self.pos + 2).map_or(true, |byte| is_whitespace(*byte));
+      if at_end {
+        self.pos += 2;
+        return;
+      }
+      self.pos += 1;
+    }
+    self.pos = self.data.len();
+  }
+}
@@ -0,0 +1,735 @@
+use crate::file_types::{self, FileTypeRegistry};
+use crate::ooxml;
+use crate::site_index::CollectSink;
+use crate::sniff;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(120);
+const MAX_SCAN_THREADS: usize = 16;
+/// Warnings kept per scan; further failures are still counted in `ScanCounts::skipped`.
+const MAX_SCAN_WARNINGS: usize = 1000;
+/// Ignore files read in every directory, lowest precedence first.
+const IGNORE_FILE_NAMES: &[&str] = &[".gitignore", ".ignore", ".rustreaderignore"];
+/// Directories that are never descended into, matching the Docker watcher's exclusions.
+const ALWAYS_SKIPPED_DIRS: &[&str] = &[".git"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanFile {
+  pub(crate) virtual_path: String,
+  pub(crate) abs_path: String,
+  pub(crate) category: String,
+  /// Resolved target when the file was reached through a symlink of its own.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) link_target: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) size: Option<u64>,
+  /// Milliseconds since the Unix epoch.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) modified_ms: Option<u64>,
+  /// Milliseconds since the Unix epoch; absent where the filesystem does not record it.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) created_ms: Option<u64>,
+  /// highlight.js language id, set for `code` files whose language is known.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) language: Option<String>,
+  /// Set for Office documents that contain a VBA project.
+  #[serde(skip_serializing_if = "std::ops::Not::not")]
+  pub(crate) has_macros: bool,
+}
+
+impl ScanFile {
+  pub(crate) fn new(virtual_path: String, abs_path: String, category: &str) -> Self {
+    Self {
+      virtual_path,
+      abs_path,
+      category: category.to_string(),
+      link_target: None,
+      size: None,
+      modified_ms: None,
+      created_ms: None,
+      language: None,
+      has_macros: false,
+    }
+  }
+
+  /// Records the language from the file-type rule, or from a `#!` line for code files that
+  /// only a glob or sniffing put into `code`.
+  pub(crate) fn detect_language(&mut self, path: &Path, file_types: &FileTypeRegistry) {
+    self.language = file_types
+      .language(&self.virtual_path, &self.category)
+      .map(str::to_string);
+    if self.language.is_none() && self.category == "code" {
+      self.language = sniff::shebang_language_of(path).map(str::to_string);
+    }
+  }
+
+  /// Settles the category of Office documents from their package manifest, so templates and
+  /// macro-enabled variants land in the right viewer, and records whether they carry macros.
+  /// Opening the zip is not free, so scans only do it when they sniff content.
+  pub(crate) fn inspect_container(&mut self, path: &Path) {
+    if !ooxml::is_office_category(&self.category) {
+      return;
+    }
+    if let Ok(Some(info)) = ooxml::inspect(path) {
+      self.category = info.category.to_string();
+      self.has_macros = info.has_macros;
+    }
+  }
+
+  pub(crate) fn apply_metadata(&mut self, metadata: &std::fs::Metadata) {
+    self.size = Some(metadata.len());
+    self.modified_ms = metadata.modified().ok().and_then(system_time_ms);
+    self.created_ms = metadata.created().ok().and_then(system_time_ms);
+  }
+}
+
+fn system_time_ms(time: SystemTime) -> Option<u64> {
+  let elapsed = time.duration_since(UNIX_EPOCH).ok()?;
+  u64::try_from(elapsed.as_millis()).ok()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScanOptions {
+  /// Honour `.gitignore`, `.ignore` and `.rustreaderignore` files with gitignore semantics.
+  pub respect_ignore_files: bool,
+  /// Include dot-files and dot-directories (and entries flagged hidden on Windows).
+  pub include_hidden: bool,
+  /// Deepest directory level below the root that is still listed; `0` lists only the root.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub max_depth: Option<u32>,
+  /// Stop once this many supported files have been matched.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub max_files: Option<u64>,
+  /// Stop after this many milliseconds of wall-clock time.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub max_duration_ms: Option<u64>,
+  /// Descend into symlinked directories (and junctions) and list symlinked files.
+  pub follow_symlinks: bool,
+  /// Fill `size`, `modifiedMs` and `createdMs` on every matched file.
+  pub collect_metadata: bool,
+  /// Read file heads to recognise extensionless and mislabeled files, and Office manifests for
+  /// macros (slower on large trees).
+  pub sniff_content: bool,
+  /// Without `follow_symlinks`, still list symlinked files but not the content of symlinked
+  /// directories, as `Path.rglob` does. Set by the site index only.
+  #[serde(skip)]
+  pub list_symlinked_files: bool,
+  /// Walk into `.git` like any other directory. Set by the site index only.
+  #[serde(skip)]
+  pub include_git_dir: bool,
+}
+
+impl Default for ScanOptions {
+  fn default() -> Self {
+    Self {
+      respect_ignore_files: true,
+      include_hidden: true,
+      max_depth: None,
+      max_files: None,
+      max_duration_ms: None,
+      follow_symlinks: false,
+      collect_metadata: true,
+      sniff_content: false,
+      list_symlinked_files: false,
+      include_git_dir: false,
+    }
+  }
+}
+
+/// Why a walk stopped before visiting the whole tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScanTruncation {
+  MaxDepth,
+  MaxFiles,
+  MaxDuration,
+}
+
+/// A path the walker could not read, reported instead of being dropped silently. Files whose
+/// metadata alone failed are still listed, without size and times.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanWarning {
+  pub path: String,
+  /// `std::io::ErrorKind` name such as `PermissionDenied`, or `InvalidPath`.
+  pub kind: String,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScanCounts {
+  pub scanned_dirs: u64,
+  pub scanned_files: u64,
+  pub matched_files: u64,
+  pub skipped: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScanOutcome {
+  pub counts: ScanCounts,
+  pub truncated: Option<ScanTruncation>,
+  pub warnings: Vec<ScanWarning>,
+}
+
+/// Receives progress and matched files from the walker worker threads.
+pub trait ScanSink: Sync {
+  fn progress(&self, stage: &'static str, counts: ScanCounts, current_path: &Path);
+  fn files(&self, files: Vec<ScanFile>);
+}
+
+/// The category the file-type rules give `virtual_path`, the path below the scanned root.
+pub(crate) fn categorize_file(virtual_path: &str) -> Option<&'static str> {
+  file_types::current().categorize(virtual_path)
+}
+
+pub fn default_scan_threads() -> usize {
+  std::thread::available_parallelism()
+    .map(|value| value.get())
+    .unwrap_or(4)
+    .clamp(1, MAX_SCAN_THREADS)
+}
+
+/// Sorts scan output into the order the tree expects (byte order of `virtual_path`).
+pub fn sort_scan_files(files: &mut [ScanFile]) {
+  files.sort_by(|a, b| a.virtual_path.cmp(&b.virtual_path));
+}
+
+/// Ignore rules of one directory, linked to the rules inherited from its ancestors.
+struct IgnoreChain {
+  matcher: Gitignore,
+  parent: Option<Arc<IgnoreChain>>,
+}
+
+impl IgnoreChain {
+  fn load(dir: &Path, parent: Option<Arc<IgnoreChain>>) -> Option<Arc<IgnoreChain>> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for name in IGNORE_FILE_NAMES {
+      let path = dir.join(name);
+      if path.is_file() {
+        found = true;
+        let _ = builder.add(path);
+      }
+    }
+    if !found {
+      return parent;
+    }
+
+    match builder.build() {
+      Ok(matcher) if !matcher.is_empty() => Some(Arc::new(IgnoreChain { matcher, parent })),
+      _ => parent,
+    }
+  }
+
+  fn is_ignored(chain: Option<&IgnoreChain>, path: &Path, is_dir: bool) -> bool {
+    let mut current = chain;
+    while let Some(node) = current {
+      match node.matcher.matched(path, is_dir) {
+        Match::Ignore(_) => return true,
+        Match::Whitelist(_) => return false,
+        Match::None => current = node.parent.as_deref(),
+      }
+    }
+    false
+  }
+}
+
+/// Whether `options` leave out the entry `name` at `path` as hidden: a dot-file, or on Windows
+/// an entry flagged hidden.
+fn is_hidden(options: &ScanOptions, name: &std::ffi::OsStr, path: &Path) -> bool {
+  if options.include_hidden {
+    return false;
+  }
+  if name.to_string_lossy().starts_with('.') {
+    return true;
+  }
+  #[cfg(windows)]
+  {
+    use std::os::windows::fs::MetadataExt;
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+      return metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+    }
+  }
+  #[cfg(not(windows))]
+  let _ = path;
+  false
+}
+
+fn is_skipped_dir(options: &ScanOptions, name: &std::ffi::OsStr) -> bool {
+  !options.include_git_dir && ALWAYS_SKIPPED_DIRS.iter().any(|skipped| name == *skipped)
+}
+
+/// Whether a walk of `root` with `options` would reach `path`, a file or (with `is_dir`) a
+/// directory below it: the hidden-entry policy, ignore files, `.git`, symlinks and `max_depth`
+/// are checked along the way down, as the walker checks them. Watchers use this to keep their
+/// deltas to what the scan listed; the path may already be gone.
+pub fn is_listed(root: &Path, path: &Path, is_dir: bool, options: &ScanOptions) -> bool {
+  let Ok(rel) = path.strip_prefix(root) else {
+    return false;
+  };
+  let names: Vec<&std::ffi::OsStr> = rel.iter().collect();
+  let Some(last) = names.len().checked_sub(1) else {
+    return false;
+  };
+  // A file sits in the directory one level above it; a directory is visited at its own level.
+  let level = if is_dir { names.len() } else { last };
+  if options.max_depth.is_some_and(|max_depth| level as u64 > u64::from(max_depth)) {
+    return false;
+  }
+  if !options.follow_symlinks && std::fs::symlink_metadata(path).is_ok_and(|m| m.is_symlink()) {
+    let listed_link = options.list_symlinked_files && !is_dir && path.is_file();
+    if !listed_link {
+      return false;
+    }
+  }
+
+  let mut ignore = if options.respect_ignore_files {
+    IgnoreChain::load(root, None)
+  } else {
+    None
+  };
+  let mut current = root.to_path_buf();
+  for (index, name) in names.into_iter().enumerate() {
+    current.push(name);
+    let entry_is_dir = is_dir || index < last;
+    if is_hidden(options, name, &current)
+      || (entry_is_dir && is_skipped_dir(options, name))
+      || IgnoreChain::is_ignored(ignore.as_deref(), &current, entry_is_dir)
+    {
+      return false;
+    }
+    if index < last && options.respect_ignore_files {
+      ignore = IgnoreChain::load(&current, ignore);
+    }
+  }
+  true
+}
+
+/// The files a walk of `root` with `options` would list below `dir`, a directory that appeared
+/// after that walk. `dir` is walked on its own, so every file is checked with [`is_listed`] to
+/// apply the ignore files, hidden entries and depth limit of the directories above it.
+pub fn scan_new_directory(root: &Path, dir: &Path, options: &ScanOptions) -> Vec<ScanFile> {
+  let Ok(rel) = dir.strip_prefix(root) else {
+    return Vec::new();
+  };
+  let prefix = rel.to_string_lossy().replace('\\', "/");
+  // The depth left below `dir` shrinks by the levels between it and the root.
+  let dir_options = ScanOptions {
+    max_depth: options
+      .max_depth
+      .map(|max_depth| max_depth.saturating_sub(rel.iter().count() as u32)),
+    ..options.clone()
+  };
+  let sink = CollectSink::default();
+  let cancel_flag = AtomicBool::new(false);
+  if walk_supported_files(dir, &dir_options, 1, &cancel_flag, &sink).is_none() {
+    return Vec::new();
+  }
+  let mut files = sink.into_files();
+  files.retain(|file| is_listed(root, Path::new(&file.abs_path), false, options));
+  for file in &mut files {
+    file.virtual_path = format!("{}/{}", prefix, file.virtual_path);
+  }
+  files
+}
+
+/// Identity of a directory for cycle detection: device and inode where the platform has them,
+/// the canonical path elsewhere.
+#[cfg(unix)]
+type DirKey = (u64, u64);
+#[cfg(not(unix))]
+type DirKey = PathBuf;
+
+#[cfg(unix)]
+fn dir_key(path: &Path) -> Option<DirKey> {
+  use std::os::unix::fs::MetadataExt;
+  let metadata = std::fs::metadata(path).ok()?;
+  Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn dir_key(path: &Path) -> Option<DirKey> {
+  std::fs::canonicalize(path).ok()
+}
+
+/// The directories between the root and the one being visited, used to spot symlink loops.
+struct DirAncestry {
+  key: DirKey,
+  parent: Option<Arc<DirAncestry>>,
+}
+
+impl DirAncestry {
+  fn push(dir: &Path, parent: Option<Arc<DirAncestry>>) -> Option<Arc<DirAncestry>> {
+    match dir_key(dir) {
+      Some(key) => Some(Arc::new(DirAncestry { key, parent })),
+      None => parent,
+    }
+  }
+
+  fn contains(ancestry: Option<&DirAncestry>, dir: &Path) -> bool {
+    let Some(key) = dir_key(dir) else {
+      return true;
+    };
+    let mut current = ancestry;
+    while let Some(node) = current {
+      if node.key == key {
+        return true;
+      }
+      current = node.parent.as_deref();
+    }
+    false
+  }
+}
+
+#[derive(Default)]
+struct DirContext {
+  depth: u32,
+  ignore: Option<Arc<IgnoreChain>>,
+  ancestry: Option<Arc<DirAncestry>>,
+}
+
+struct WalkState<'a> {
+  root: &'a Path,
+  options: &'a ScanOptions,
+  cancel_flag: &'a AtomicBool,
+  sink: &'a dyn ScanSink,
+  started_at: Instant,
+  deadline: Option<Duration>,
+  /// Set by the first limit that ends the walk; reported ahead of a `max_depth` cut.
+  stop_reason: OnceLock<ScanTruncation>,
+  /// Set when `max_depth` left directories out, which does not stop the rest of the walk.
+  depth_truncated: AtomicBool,
+  last_emit_ms: AtomicU64,
+  scanned_dirs: AtomicU64,
+  scanned_files: AtomicU64,
+  matched_files: AtomicU64,
+  skipped: AtomicU64,
+  warnings: Mutex<Vec<ScanWarning>>,
+  file_types: Arc<FileTypeRegistry>,
+}
+
+impl WalkState<'_> {
+  fn counts(&self) -> ScanCounts {
+    ScanCounts {
+      scanned_dirs: self.scanned_dirs.load(Ordering::Relaxed),
+      scanned_files: self.scanned_files.load(Ordering::Relaxed),
+      matched_files: self.matched_files.load(Ordering::Relaxed),
+      skipped: self.skipped.load(Ordering::Relaxed),
+    }
+  }
+
+  fn skip(&self, path: &Path, kind: String, message: String) {
+    self.skipped.fetch_add(1, Ordering::Relaxed);
+    self.warn(path, kind, message);
+  }
+
+  fn skip_io(&self, path: &Path, error: &std::io::Error) {
+    self.skip(path, format!("{:?}", error.kind()), error.to_string());
+  }
+
+  /// Records a problem with a path that is still listed, so it is not counted as skipped.
+  fn warn(&self, path: &Path, kind: String, message: String) {
+    let Ok(mut warnings) = self.warnings.lock() else {
+      return;
+    };
+    if warnings.len() < MAX_SCAN_WARNINGS {
+      warnings.push(ScanWarning {
+        path: path.to_string_lossy().into_owned(),
+        kind,
+        message,
+      });
+    }
+  }
+
+  fn cancelled(&self) -> bool {
+    self.cancel_flag.load(Ordering::Relaxed)
+  }
+
+  fn stopped(&self) -> bool {
+    self.stop_reason.get().is_some()
+  }
+
+  fn truncate(&self, reason: ScanTruncation) {
+    match reason {
+      ScanTruncation::MaxDepth => self.depth_truncated.store(true, Ordering::Relaxed),
+      ScanTruncation::MaxFiles | ScanTruncation::MaxDuration => {
+        let _ = self.stop_reason.set(reason);
+      }
+    }
+  }
+
+  fn truncation(&self) -> Option<ScanTruncation> {
+    let depth = self.depth_truncated.load(Ordering::Relaxed);
+    self
+      .stop_reason
+      .get()
+      .copied()
+      .or(depth.then_some(ScanTruncation::MaxDepth))
+  }
+
+  /// Reserves a slot for one more matched file, or records the `max_files` truncation.
+  fn claim_match(&self) -> bool {
+    let Some(max_files) = self.options.max_files else {
+      self.matched_files.fetch_add(1, Ordering::Relaxed);
+      return true;
+    };
+    let claimed = self
+      .matched_files
+      .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+        (current < max_files).then_some(current + 1)
+      })
+      .is_ok();
+    if !claimed {
+      self.truncate(ScanTruncation::MaxFiles);
+    }
+    claimed
+  }
+
+  fn report_progress(&self, current_path: &Path) {
+    let now_ms = u64::try_from(self.started_at.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let last_ms = self.last_emit_ms.load(Ordering::Relaxed);
+    if now_ms.saturating_sub(last_ms) < PROGRESS_INTERVAL.as_millis() as u64 {
+      return;
+    }
+    // Only the worker that wins the exchange reports, the others skip this tick.
+    if self
+      .last_emit_ms
+      .compare_exchange(last_ms, now_ms, Ordering::Relaxed, Ordering::Relaxed)
+      .is_ok()
+    {
+      self.sink.progress("progress", self.counts(), current_path);
+    }
+  }
+}
+
+/// Walks `root` on a work-stealing pool of `threads` workers, handing matched files to `sink`
+/// one directory at a time. Returns `None` when the scan was cancelled through `cancel_flag`;
+/// hitting one of the `options` limits still returns the partial result, marked as truncated.
+pub fn walk_supported_files(
+  root: &Path,
+  options: &ScanOptions,
+  threads: usize,
+  cancel_flag: &AtomicBool,
+  sink: &dyn ScanSink,
+) -> Option<ScanOutcome> {
+  let state = WalkState {
+    root,
+    options,
+    cancel_flag,
+    sink,
+    started_at: Instant::now(),
+    deadline: options.max_duration_ms.map(Duration::from_millis),
+    stop_reason: OnceLock::new(),
+    depth_truncated: AtomicBool::new(false),
+    last_emit_ms: AtomicU64::new(0),
+    scanned_dirs: AtomicU64::new(0),
+    scanned_files: AtomicU64::new(0),
+    matched_files: AtomicU64::new(0),
+    skipped: AtomicU64::new(0),
+    warnings: Mutex::new(Vec::new()),
+    file_types: file_types::current(),
+  };
+
+  sink.progress("start", state.counts(), root);
+
+  let pool = rayon::ThreadPoolBuilder::new()
+    .num_threads(threads.clamp(1, MAX_SCAN_THREADS))
+    .thread_name(|index| format!("rustreader-walk-{index}"))
+    .build();
+  match pool {
+    Ok(pool) => pool.scope(|scope| visit_root(scope, &state)),
+    Err(_) => rayon::scope(|scope| visit_root(scope, &state)),
+  }
+
+  let counts = state.counts();
+  if state.cancelled() {
+    sink.progress("cancelled", counts, root);
+    return None;
+  }
+
+  sink.progress("done", counts, root);
+  let truncated = state.truncation();
+  let warnings = match state.warnings.into_inner() {
+    Ok(warnings) => warnings,
+    Err(poisoned) => poisoned.into_inner(),
+  };
+  Some(ScanOutcome {
+    counts,
+    truncated,
+    warnings,
+  })
+}
+
+fn visit_root<'scope>(scope: &rayon::Scope<'scope>, state: &'scope WalkState<'_>) {
+  visit_dir(scope, state, state.root.to_path_buf(), DirContext::default());
+}
+
+fn visit_dir<'scope>(
+  scope: &rayon::Scope<'scope>,
+  state: &'scope WalkState<'_>,
+  dir: PathBuf,
+  context: DirContext,
+) {
+  if state.cancelled() || state.stopped() {
+    return;
+  }
+  if state.deadline.is_some_and(|deadline| state.started_at.elapsed() >= deadline) {
+    state.truncate(ScanTruncation::MaxDuration);
+    return;
+  }
+
+  state.scanned_dirs.fetch_add(1, Ordering::Relaxed);
+  state.report_progress(&dir);
+
+  let ignore = if state.options.respect_ignore_files {
+    IgnoreChain::load(&dir, context.ignore)
+  } else {
+    None
+  };
+  let ancestry = if state.options.follow_symlinks {
+    DirAncestry::push(&dir, context.ancestry)
+  } else {
+    None
+  };
+
+  let entries = match std::fs::read_dir(&dir) {
+    Ok(entries) => entries,
+    Err(error) => {
+      state.skip_io(&dir, &error);
+      return;
+    }
+  };
+
+  let mut matched = Vec::new();
+  for entry in entries {
+    let entry = match entry {
+      Ok(entry) => entry,
+      Err(error) => {
+        state.skip_io(&dir, &error);
+        continue;
+      }
+    };
+
+    let file_type = match entry.file_type() {
+      Ok(file_type) => file_type,
+      Err(error) => {
+        state.skip_io(&entry.path(), &error);
+        continue;
+      }
+    };
+
+    let path = entry.path();
+    if is_hidden(state.options, &entry.file_name(), &path) {
+      continue;
+    }
+
+    let mut link_target = None;
+    let mut link_metadata = None;
+    let (is_dir, is_file) = if file_type.is_symlink() {
+      let follow = state.options.follow_symlinks;
+      if !follow && !state.options.list_symlinked_files {
+        continue;
+      }
+      let target = match std::fs::metadata(&path) {
+        Ok(target) if !follow && target.is_dir() => continue,
+        Ok(target) => target,
+        // `rglob` passes over broken links without a word.
+        Err(_) if !follow => continue,
+        Err(error) => {
+          state.skip_io(&path, &error);
+          continue;
+        }
+      };
+      link_target = std::fs::canonicalize(&path).ok();
+      let kind = (target.is_dir(), target.is_file());
+      link_metadata = Some(target);
+      kind
+    } else {
+      (file_type.is_dir(), file_type.is_file())
+    };
+
+    if is_dir {
+      let skipped = is_skipped_dir(state.options, &entry.file_name());
+      if skipped || IgnoreChain::is_ignored(ignore.as_deref(), &path, true) {
+        continue;
+      }
+      if link_target.is_some() && DirAncestry::contains(ancestry.as_deref(), &path) {
+        continue;
+      }
+      if state.options.max_depth.is_some_and(|max_depth| context.depth >= max_depth) {
+        state.truncate(ScanTruncation::MaxDepth);
+        continue;
+      }
+      let context = DirContext {
+        depth: context.depth + 1,
+        ignore: ignore.clone(),
+        ancestry: ancestry.clone(),
+      };
+      scope.spawn(move |scope| visit_dir(scope, state, path, context));
+      continue;
+    }
+    if !is_file || IgnoreChain::is_ignored(ignore.as_deref(), &path, false) {
+      continue;
+    }
+
+    state.scanned_files.fetch_add(1, Ordering::Relaxed);
+    let virtual_path = match path.strip_prefix(state.root) {
+      Ok(rel) => rel.to_string_lossy().replace('\\', "/"),
+      Err(error) => {
+        state.skip(&path, "InvalidPath".to_string(), error.to_string());
+        continue;
+      }
+    };
+    let mut category = state.file_types.categorize(&virtual_path);
+    if state.options.sniff_content {
+      match sniff::detect_category(&path, category) {
+        Ok(detected) => category = detected,
+        // A file the name already placed is listed with that category, as watchers do.
+        Err(error) if category.is_some() => {
+          state.warn(&path, format!("{:?}", error.kind()), error.to_string());
+        }
+        Err(error) => {
+          state.skip_io(&path, &error);
+          continue;
+        }
+      }
+    }
+    let Some(category) = category else {
+      state.report_progress(&path);
+      continue;
+    };
+
+    if !state.claim_match() {
+      break;
+    }
+
+    let mut file = ScanFile::new(virtual_path, path.to_string_lossy().into_owned(), category);
+    file.link_target = link_target.map(|target| target.to_string_lossy().into_owned());
+    if state.options.sniff_content {
+      file.inspect_container(&path);
+    }
+    file.detect_language(&path, &state.file_types);
+    if state.options.collect_metadata {
+      match link_metadata.map_or_else(|| entry.metadata(), Ok) {
+        Ok(metadata) => file.apply_metadata(&metadata),
+        // The file is listed without size and times.
+        Err(error) => state.warn(&path, format!("{:?}", error.kind()), error.to_string()),
+      }
+    }
+    matched.push(file);
+    state.report_progress(&path);
+  }
+
+  if !matched.is_empty() {
+    state.sink.files(matched);
+  }
+}
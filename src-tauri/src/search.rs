@@ -0,0 +1,174 @@
+use crate::extract;
+use crate::scan::ScanFile;
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Larger files are skipped; they are rarely prose and would stall a worker.
+const MAX_SEARCH_FILE_LEN: u64 = 16 * 1024 * 1024;
+/// A NUL byte in this much of the head marks the file as binary, as grep does.
+const BINARY_PROBE_LEN: usize = 8 * 1024;
+/// Characters kept on each side of the match in a snippet.
+const SNIPPET_CONTEXT_CHARS: usize = 80;
+const MAX_SNIPPET_MATCH_CHARS: usize = 200;
+pub(crate) const MAX_MATCHES_PER_FILE: usize = 100;
+pub(crate) const DEFAULT_MAX_MATCHES: u64 = 2000;
+/// Categories whose files are plain text on disk.
+const TEXT_CATEGORIES: &[&str] = &["markdown", "mindmap", "marpit", "text", "code"];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchOptions {
+  pub(crate) case_sensitive: bool,
+  /// Treat the query as a regular expression instead of a literal string.
+  pub(crate) regex: bool,
+  /// Only search files in these categories; defaults to every searchable category.
+  pub(crate) categories: Option<Vec<String>>,
+  /// Stop once this many lines have matched.
+  pub(crate) max_matches: Option<u64>,
+}
+
+/// One matching line.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchMatch {
+  pub(crate) virtual_path: String,
+  pub(crate) abs_path: String,
+  pub(crate) category: String,
+  /// 1-based.
+  pub(crate) line_number: u64,
+  /// The line around the first match, shortened with `…` where it was cut.
+  pub(crate) snippet: String,
+  /// Match range inside `snippet`, in UTF-16 code units so it can be used with JS strings.
+  pub(crate) match_start: usize,
+  pub(crate) match_end: usize,
+}
+
+pub struct ContentMatcher {
+  regex: Regex,
+  categories: Vec<String>,
+}
+
+impl ContentMatcher {
+  pub fn new(query: &str, options: &SearchOptions) -> Result<Self, String> {
+    let pattern = if options.regex {
+      query.to_string()
+    } else {
+      regex::escape(query)
+    };
+    let regex = RegexBuilder::new(&pattern)
+      .case_insensitive(!options.case_sensitive)
+      .build()
+      .map_err(|error| format!("无效的正则表达式: {}", error))?;
+    let categories = match &options.categories {
+      Some(categories) => categories.clone(),
+      None => TEXT_CATEGORIES
+        .iter()
+        .chain(extract::DOCUMENT_CATEGORIES)
+        .map(|category| category.to_string())
+        .collect(),
+    };
+    Ok(Self { regex, categories })
+  }
+
+  pub fn wants(&self, category: &str) -> bool {
+    self.categories.iter().any(|wanted| wanted == category)
+  }
+
+  /// Matching lines of `file`, at most `limit` of them. Returns `Ok(None)` for files that are
+  /// skipped because they look binary or are too large.
+  pub fn search_file(
+    &self,
+    file: &ScanFile,
+    limit: usize,
+  ) -> std::io::Result<Option<Vec<SearchMatch>>> {
+    let Some(content) = searchable_text(Path::new(&file.abs_path), &file.category)? else {
+      return Ok(None);
+    };
+
+    let mut matches = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+      if matches.len() >= limit {
+        break;
+      }
+      // Empty matches (e.g. `a*`) would flag every line.
+      let Some(found) = self.regex.find_iter(line).find(|found| !found.is_empty()) else {
+        continue;
+      };
+      let (snippet, match_start, match_end) = snippet(line, found.start(), found.end());
+      matches.push(SearchMatch {
+        virtual_path: file.virtual_path.clone(),
+        abs_path: file.abs_path.clone(),
+        category: file.category.clone(),
+        line_number: index as u64 + 1,
+        snippet,
+        match_start,
+        match_end,
+      });
+    }
+    Ok(Some(matches))
+  }
+}
+
+/// The text to search in a file: its content for text categories, the extracted text for Office
+/// documents and PDFs.
+pub(crate) fn searchable_text(path: &Path, category: &str) -> std::io::Result<Option<String>> {
+  if extract::is_document_category(category) {
+    extract::extract_text(path, category)
+  } else {
+    read_text(path)
+  }
+}
+
+/// The content of a text file, decoded lossily as UTF-8. Returns `Ok(None)` for files that look
+/// binary or are too large to search.
+pub(crate) fn read_text(path: &Path) -> std::io::Result<Option<String>> {
+  let file = File::open(path)?;
+  if file.metadata()?.len() > MAX_SEARCH_FILE_LEN {
+    return Ok(None);
+  }
+  let mut content = Vec::new();
+  file.take(MAX_SEARCH_FILE_LEN).read_to_end(&mut content)?;
+  if content[..content.len().min(BINARY_PROBE_LEN)].contains(&0) {
+    return Ok(None);
+  }
+  let content = content.strip_prefix(b"\xef\xbb\xbf").unwrap_or(&content);
+  Ok(Some(String::from_utf8_lossy(content).into_owned()))
+}
+
+pub(crate) fn is_searchable_category(category: &str) -> bool {
+  TEXT_CATEGORIES.contains(&category) || extract::is_document_category(category)
+}
+
+fn snippet(line: &str, start: usize, end: usize) -> (String, usize, usize) {
+  let before = line[..start].trim_start();
+  let after = line[end..].trim_end();
+
+  let mut snippet = String::new();
+  let skipped = before.chars().count().saturating_sub(SNIPPET_CONTEXT_CHARS);
+  if skipped > 0 {
+    snippet.push('…');
+  }
+  snippet.extend(before.chars().skip(skipped));
+  let match_start = utf16_len(&snippet);
+
+  let matched = &line[start..end];
+  snippet.extend(matched.chars().take(MAX_SNIPPET_MATCH_CHARS));
+  let match_end = utf16_len(&snippet);
+  if matched.chars().count() > MAX_SNIPPET_MATCH_CHARS {
+    snippet.push('…');
+    return (snippet, match_start, match_end);
+  }
+
+  snippet.extend(after.chars().take(SNIPPET_CONTEXT_CHARS));
+  if after.chars().count() > SNIPPET_CONTEXT_CHARS {
+    snippet.push('…');
+  }
+  (snippet, match_start, match_end)
+}
+
+fn utf16_len(value: &str) -> usize {
+  value.encode_utf16().count()
+}
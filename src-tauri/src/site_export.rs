@@ -0,0 +1,114 @@
+//! A scanned folder as a self-contained static site: the web UI, the supported files and an
+//! `index.json`, ready to be zipped or served by any file server.
+
+use crate::site_index::{self, INDEX_FILE_NAME};
+use regex::{Captures, Regex};
+use std::collections::HashSet;
+use std::path::Path;
+
+pub const DEFAULT_SITE_NAME: &str = "文件浏览器";
+const SITE_NAME_PREFIX: &str = "rustreader - ";
+const UI_PAGE: &str = "index.html";
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportSummary {
+  /// Supported files copied from the source folder.
+  pub files: u64,
+  /// Source files left out because the UI or the index uses their path.
+  pub skipped: Vec<String>,
+}
+
+/// Copies `ui_files` (paths relative to the site root) and the supported files of `src` into
+/// `dest`, which must be empty or missing, and writes the `index.json` listing those files.
+/// The page title and brand text of `index.html` are set to `site_name`.
+pub fn export_site(
+  src: &Path,
+  dest: &Path,
+  site_name: Option<&str>,
+  ui_files: &[(String, Vec<u8>)],
+) -> Result<ExportSummary, String> {
+  if dest.exists() {
+    let mut entries = std::fs::read_dir(dest)
+      .map_err(|error| format!("无法读取导出目录 {}: {}", dest.display(), error))?;
+    if entries.next().is_some() {
+      return Err(format!("导出目录不为空: {}", dest.display()));
+    }
+  }
+  // Listed before anything is written, so a destination inside `src` does not list itself.
+  let files = site_index::scan_supported_files(src)?;
+
+  let mut reserved: HashSet<&str> = ui_files.iter().map(|(path, _)| path.as_str()).collect();
+  reserved.insert(INDEX_FILE_NAME);
+  let (files, skipped): (Vec<_>, Vec<_>) = files.into_iter().partition(|file| {
+    !reserved.contains(file.virtual_path.as_str()) && !site_index::is_site_file(&file.virtual_path)
+  });
+
+  for (path, content) in ui_files {
+    let target = dest.join(path);
+    create_parent(&target)?;
+    let written = if path == UI_PAGE {
+      let html = String::from_utf8_lossy(content);
+      std::fs::write(&target, apply_site_name(&html, site_name.unwrap_or_default()))
+    } else {
+      std::fs::write(&target, content)
+    };
+    written.map_err(|error| format!("写入 {} 失败: {}", target.display(), error))?;
+  }
+
+  for file in &files {
+    let target = dest.join(&file.virtual_path);
+    create_parent(&target)?;
+    std::fs::copy(&file.abs_path, &target)
+      .map_err(|error| format!("复制 {} 失败: {}", file.abs_path, error))?;
+  }
+
+  let summary = ExportSummary {
+    files: files.len() as u64,
+    skipped: skipped.into_iter().map(|file| file.virtual_path).collect(),
+  };
+  let content = site_index::site_index_json(&site_index::group_files(files))?;
+  let index_path = dest.join(INDEX_FILE_NAME);
+  std::fs::write(&index_path, content.as_bytes())
+    .map_err(|error| format!("写入 {} 失败: {}", index_path.display(), error))?;
+  Ok(summary)
+}
+
+fn create_parent(path: &Path) -> Result<(), String> {
+  let Some(parent) = path.parent() else {
+    return Ok(());
+  };
+  std::fs::create_dir_all(parent)
+    .map_err(|error| format!("创建目录失败 ({}): {}", parent.display(), error))
+}
+
+/// Puts `site_name` into the first `<title>` and the `brandText` span, as `regen-watcher.sh`
+/// does for the Docker site. A `rustreader - ` prefix is dropped; an empty name means
+/// [`DEFAULT_SITE_NAME`].
+pub fn apply_site_name(html: &str, site_name: &str) -> String {
+  let mut name = site_name.trim();
+  let prefixed = name
+    .get(..SITE_NAME_PREFIX.len())
+    .is_some_and(|head| head.eq_ignore_ascii_case(SITE_NAME_PREFIX));
+  if prefixed {
+    name = name[SITE_NAME_PREFIX.len()..].trim();
+  }
+  if name.is_empty() {
+    name = DEFAULT_SITE_NAME;
+  }
+  let name = escape_html(name);
+
+  let replace_inner = |caps: &Captures| format!("{}{}{}", &caps[1], name, &caps[3]);
+  let title = Regex::new(r"(?s)(<title>)(.*?)(</title>)").expect("valid title pattern");
+  let brand = Regex::new(r#"(?s)(<span[^>]*id="brandText"[^>]*>)(.*?)(</span>)"#)
+    .expect("valid brand pattern");
+  let html = title.replacen(html, 1, &replace_inner);
+  brand.replacen(&html, 1, &replace_inner).into_owned()
+}
+
+fn escape_html(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
@@ -0,0 +1,247 @@
+//! `index.json` for the static/Docker deployment, in the exact shape and byte layout that
+//! `ui/generate.py` writes.
+//!
+//! The walk matches `Path.rglob("*")`: hidden files and `.git` are listed, ignore files are
+//! not honoured, and symlinked files are listed without descending into symlinked
+//! directories. Files are categorised by name alone, as the script does. Both leave out the
+//! site's own page, assets, script and index.
+
+use crate::file_types::CATEGORY_ORDER;
+use crate::scan::{self, ScanCounts, ScanFile, ScanOptions, ScanSink};
+use serde::ser::SerializeMap;
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Mutex;
+
+pub const INDEX_FILE_NAME: &str = "index.json";
+/// Files at the top of a site that belong to the site itself: the page, the index and the
+/// script that writes it. Like `generate.py`, the index leaves them out.
+const SITE_FILES: &[&str] = &["index.html", INDEX_FILE_NAME, "generate.py"];
+/// Top-level directories that belong to the site itself.
+const SITE_DIRS: &[&str] = &["assets"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteIndex {
+  pub groups: Vec<SiteGroup>,
+  pub totals_by_type: CategoryTotals,
+  pub total_files: u64,
+}
+
+/// The supported files directly inside one directory; `path` is `.` for the root.
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteGroup {
+  pub path: String,
+  pub categories: Vec<SiteCategory>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SiteCategory {
+  #[serde(rename = "type")]
+  pub category: String,
+  pub files: Vec<String>,
+  pub count: u64,
+}
+
+/// Counts for every category in `CATEGORY_ORDER`, serialized as an object in that order.
+#[derive(Debug, Clone)]
+pub struct CategoryTotals(Vec<(&'static str, u64)>);
+
+impl Serialize for CategoryTotals {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(self.0.len()))?;
+    for (category, count) in &self.0 {
+      map.serialize_entry(category, count)?;
+    }
+    map.end()
+  }
+}
+
+/// Gathers the walker's output; for scans whose progress nobody watches.
+#[derive(Default)]
+pub(crate) struct CollectSink {
+  files: Mutex<Vec<ScanFile>>,
+}
+
+impl CollectSink {
+  /// The collected files in tree order.
+  pub(crate) fn into_files(self) -> Vec<ScanFile> {
+    let mut files = self.files.into_inner().unwrap_or_else(|poisoned| poisoned.into_inner());
+    scan::sort_scan_files(&mut files);
+    files
+  }
+}
+
+impl ScanSink for CollectSink {
+  fn progress(&self, _stage: &'static str, _counts: ScanCounts, _current_path: &Path) {}
+
+  fn files(&self, files: Vec<ScanFile>) {
+    if let Ok(mut collected) = self.files.lock() {
+      collected.extend(files);
+    }
+  }
+}
+
+/// Scans `root` and groups its supported files by directory and category.
+pub fn build_site_index(root: &Path) -> Result<SiteIndex, String> {
+  build_site_index_for(root, &root.join(INDEX_FILE_NAME))
+}
+
+/// [`build_site_index`] for an index written to `out`, which is left out of the listing along
+/// with its temporary file when `--out` points below `root`.
+pub(crate) fn build_site_index_for(root: &Path, out: &Path) -> Result<SiteIndex, String> {
+  let tmp_path = temp_index_path(out);
+  let written = [out, tmp_path.as_path()].map(|path| virtual_path_below(root, path));
+  let mut files = scan_site_files(root)?;
+  files.retain(|file| !written.iter().flatten().any(|path| *path == file.virtual_path));
+  Ok(group_files(files))
+}
+
+/// The virtual path `path` would have in a scan of `root`, if it lies below it. `path` need
+/// not exist yet; either may be relative.
+fn virtual_path_below(root: &Path, path: &Path) -> Option<String> {
+  let root = root.canonicalize().ok()?;
+  let parent = match path.parent() {
+    Some(parent) if !parent.as_os_str().is_empty() => parent,
+    _ => Path::new("."),
+  };
+  let path = parent.canonicalize().ok()?.join(path.file_name()?);
+  let relative = path.strip_prefix(&root).ok()?;
+  let names: Vec<_> = relative.iter().map(|name| name.to_string_lossy()).collect();
+  Some(names.join("/"))
+}
+
+/// Whether `virtual_path` is one of the site's own files rather than its content.
+pub fn is_site_file(virtual_path: &str) -> bool {
+  if SITE_FILES.contains(&virtual_path) {
+    return true;
+  }
+  virtual_path
+    .split_once('/')
+    .is_some_and(|(dir, _)| SITE_DIRS.contains(&dir))
+}
+
+/// The files `index.json` lists for `root`, sorted by virtual path.
+pub(crate) fn scan_site_files(root: &Path) -> Result<Vec<ScanFile>, String> {
+  let mut files = scan_supported_files(root)?;
+  files.retain(|file| !is_site_file(&file.virtual_path));
+  Ok(files)
+}
+
+/// Every file below `root` the site could show, including the site's own files, sorted by
+/// virtual path.
+pub(crate) fn scan_supported_files(root: &Path) -> Result<Vec<ScanFile>, String> {
+  if !root.exists() {
+    return Err(format!("目标不存在: {}", root.display()));
+  }
+  if !root.is_dir() {
+    return Err(format!("不是目录: {}", root.display()));
+  }
+
+  let options = ScanOptions {
+    respect_ignore_files: false,
+    include_hidden: true,
+    follow_symlinks: false,
+    collect_metadata: false,
+    sniff_content: false,
+    list_symlinked_files: true,
+    include_git_dir: true,
+    ..ScanOptions::default()
+  };
+  let sink = CollectSink::default();
+  scan::walk_supported_files(
+    root,
+    &options,
+    scan::default_scan_threads(),
+    &AtomicBool::new(false),
+    &sink,
+  )
+  .ok_or_else(|| "扫描已取消".to_string())?;
+  Ok(sink.into_files())
+}
+
+/// Groups files sorted by virtual path into the `index.json` layout.
+pub(crate) fn group_files(files: Vec<ScanFile>) -> SiteIndex {
+  let mut grouped: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+  for file in files {
+    let dir = match file.virtual_path.rsplit_once('/') {
+      Some((dir, _)) => dir.to_string(),
+      None => ".".to_string(),
+    };
+    grouped
+      .entry(dir)
+      .or_default()
+      .entry(file.category)
+      .or_default()
+      .push(file.virtual_path);
+  }
+
+  let mut totals: Vec<(&'static str, u64)> =
+    CATEGORY_ORDER.iter().map(|category| (*category, 0)).collect();
+  let mut groups: Vec<SiteGroup> = grouped
+    .into_iter()
+    .map(|(path, mut by_category)| {
+      let mut categories = Vec::new();
+      for (category, total) in totals.iter_mut() {
+        let Some(files) = by_category.remove(*category) else {
+          continue;
+        };
+        let count = files.len() as u64;
+        *total += count;
+        categories.push(SiteCategory {
+          category: category.to_string(),
+          files,
+          count,
+        });
+      }
+      SiteGroup { path, categories }
+    })
+    .collect();
+  // Root first, then the rest in code point order, as `generate.py` sorts them.
+  groups.sort_by(|a, b| (a.path != ".", &a.path).cmp(&(b.path != ".", &b.path)));
+
+  let total_files = totals.iter().map(|(_, count)| count).sum();
+  SiteIndex {
+    groups,
+    totals_by_type: CategoryTotals(totals),
+    total_files,
+  }
+}
+
+/// The JSON text `json.dumps(payload, indent=2, ensure_ascii=False)` produces, without a
+/// trailing newline.
+pub fn site_index_json(index: &SiteIndex) -> Result<String, String> {
+  serde_json::to_string_pretty(index).map_err(|error| format!("序列化索引失败: {}", error))
+}
+
+/// Writes the index of `root` to `out`, or to `index.json` inside `root`, and returns the path
+/// written and the number of files listed.
+pub fn write_site_index(root: &Path, out: Option<&Path>) -> Result<(PathBuf, u64), String> {
+  let out = out.map_or_else(|| root.join(INDEX_FILE_NAME), Path::to_path_buf);
+  let index = build_site_index_for(root, &out)?;
+  let content = site_index_json(&index)?;
+  replace_index_file(&out, &content)?;
+  Ok((out, index.total_files))
+}
+
+/// The file an index is written to before it is renamed over `out`.
+pub(crate) fn temp_index_path(out: &Path) -> PathBuf {
+  out.with_extension("tmp")
+}
+
+/// Writes through a temporary file and a rename, so a web server never serves a half-written
+/// index.
+pub(crate) fn replace_index_file(out: &Path, content: &str) -> Result<(), String> {
+  let tmp_path = temp_index_path(out);
+  std::fs::write(&tmp_path, content.as_bytes())
+    .map_err(|error| format!("写入 {} 失败: {}", tmp_path.display(), error))?;
+
+  if std::fs::rename(&tmp_path, out).is_err() {
+    let _ = std::fs::remove_file(out);
+    std::fs::rename(&tmp_path, out)
+      .map_err(|error| format!("替换 {} 失败: {}", out.display(), error))?;
+  }
+  Ok(())
+}
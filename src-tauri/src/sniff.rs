@@ -0,0 +1,135 @@
+use crate::file_types;
+use crate::ooxml;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// How much of the file head is inspected for signatures and the text heuristic.
+const SNIFF_HEAD_LEN: u64 = 8 * 1024;
+const SHEBANG_LINE_LEN: u64 = 256;
+/// Share of control bytes above which a UTF-8 buffer is no longer treated as text.
+const MAX_CONTROL_BYTES_PERCENT: usize = 1;
+/// Major brands of HEIF and AVIF still images and image sequences.
+const HEIF_BRANDS: &[&[u8]] = &[
+  b"heic", b"heix", b"heim", b"heis", b"hevc", b"hevx", b"hevm", b"hevs", b"mif1", b"msf1",
+  b"avif", b"avis",
+];
+
+/// Picks the category for `path` from its content, falling back to `by_name` (the registry
+/// result) when the content is not recognised.
+///
+/// Binary signatures win over the name so a PDF saved as `.txt` still opens as a PDF; only an
+/// MPEG-4 container named as audio or video keeps its name, since the brand rarely says which.
+/// The text heuristic only applies to files without an extension, such as `LICENSE`.
+pub fn detect_category(
+  path: &Path,
+  by_name: Option<&'static str>,
+) -> std::io::Result<Option<&'static str>> {
+  let mut file = File::open(path)?;
+  let mut head = Vec::with_capacity(SNIFF_HEAD_LEN as usize);
+  (&mut file).take(SNIFF_HEAD_LEN).read_to_end(&mut head)?;
+
+  if let Some(category) = sniff_signature(&head, by_name) {
+    return Ok(Some(category));
+  }
+  if head.starts_with(b"PK\x03\x04") {
+    if let Some(info) = ooxml::inspect(path)? {
+      return Ok(Some(info.category));
+    }
+  }
+  if by_name.is_none() {
+    // A bare frame sync is too weak to overrule a name, e.g. a UTF-16 text file's BOM.
+    if is_mpeg_audio_frame(&head) {
+      return Ok(Some("audio"));
+    }
+    if first_line(&head).and_then(file_types::shebang_language).is_some() {
+      return Ok(Some("code"));
+    }
+    if path.extension().is_none() && looks_like_text(&head) {
+      return Ok(Some("text"));
+    }
+  }
+  Ok(by_name)
+}
+
+/// The interpreter language named by the `#!` line of `path`, if any.
+pub fn shebang_language_of(path: &Path) -> Option<&'static str> {
+  let mut head = Vec::with_capacity(SHEBANG_LINE_LEN as usize);
+  File::open(path)
+    .ok()?
+    .take(SHEBANG_LINE_LEN)
+    .read_to_end(&mut head)
+    .ok()?;
+  first_line(&head).and_then(file_types::shebang_language)
+}
+
+fn first_line(head: &[u8]) -> Option<&str> {
+  if !head.starts_with(b"#!") {
+    return None;
+  }
+  let end = head.iter().position(|byte| *byte == b'\n').unwrap_or(head.len());
+  std::str::from_utf8(&head[..end]).ok()
+}
+
+fn sniff_signature(head: &[u8], by_name: Option<&'static str>) -> Option<&'static str> {
+  if head.starts_with(b"\x89PNG\r\n\x1a\n")
+    || head.starts_with(b"\xff\xd8\xff")
+    || head.starts_with(b"GIF87a")
+    || head.starts_with(b"GIF89a")
+    || (head.starts_with(b"RIFF") && head.get(8..12) == Some(b"WEBP"))
+  {
+    return Some("images");
+  }
+  if head.starts_with(b"%PDF-") {
+    return Some("pdf");
+  }
+  if head.get(4..8) == Some(b"ftyp") {
+    // ISO base media: HEIF images and audio-only MPEG-4 have brands of their own, but `.m4a`
+    // files often carry a generic one like `isom` or `mp42`, so an audio or video name stays.
+    return match head.get(8..12) {
+      Some(brand) if HEIF_BRANDS.contains(&brand) => Some("images"),
+      _ if matches!(by_name, Some("audio" | "video")) => by_name,
+      Some(b"M4A ") | Some(b"M4B ") | Some(b"M4P ") => Some("audio"),
+      _ => Some("video"),
+    };
+  }
+  if head.starts_with(b"OggS") {
+    let has_theora = head.windows(7).any(|window| window == b"\x80theora");
+    return Some(if has_theora { "video" } else { "audio" });
+  }
+  if head.starts_with(b"fLaC") || head.starts_with(b"ID3") {
+    return Some("audio");
+  }
+  None
+}
+
+/// An MPEG audio frame header: 11 sync bits, a valid layer and a usable bitrate index.
+fn is_mpeg_audio_frame(head: &[u8]) -> bool {
+  let [first, second, third, ..] = head else {
+    return false;
+  };
+  *first == 0xff
+    && second & 0xe0 == 0xe0
+    && second & 0x06 != 0
+    && !matches!(second, 0xfe | 0xff)
+    && third & 0xf0 != 0xf0
+}
+
+fn looks_like_text(head: &[u8]) -> bool {
+  if head.is_empty() || head.contains(&0) {
+    return false;
+  }
+  // The head may end in the middle of a multi-byte character.
+  let valid = match std::str::from_utf8(head) {
+    Ok(_) => true,
+    Err(error) => error.error_len().is_none() && error.valid_up_to() > 0,
+  };
+  if !valid {
+    return false;
+  }
+  let control = head
+    .iter()
+    .filter(|byte| **byte < 0x20 && !matches!(byte, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b))
+    .count();
+  control * 100 <= head.len() * MAX_CONTROL_BYTES_PERCENT
+}
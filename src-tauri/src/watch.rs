@@ -0,0 +1,561 @@
+use crate::file_types;
+use crate::site_index;
+use crate::scan::{self, categorize_file, ScanFile, ScanOptions};
+use crate::sniff;
+use notify::event::{AccessKind, AccessMode, ModifyKind, RemoveKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+pub(crate) const FOLDER_CHANGED_EVENT: &str = "rustreader_folder_changed";
+const FOLDER_WATCH_DEBOUNCE: Duration = Duration::from_millis(250);
+pub(crate) const FILE_CHANGED_EVENT: &str = "rustreader_file_changed";
+const FILE_WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+/// Longer than the folder debounce: copying a batch of files should cost one rescan.
+const SITE_INDEX_DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FolderChange {
+  /// `add`, `remove`, `rename` or `modify`.
+  kind: &'static str,
+  virtual_path: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  old_virtual_path: Option<String>,
+  /// The entry as `scan_path` would list it; absent for removals.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  file: Option<ScanFile>,
+  /// Set when a whole directory went away; the removal applies to everything below it.
+  is_dir: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FolderChangedEvent {
+  root: String,
+  changes: Vec<FolderChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FileChangedEvent {
+  path: String,
+  /// The file no longer exists, e.g. it was deleted or moved away.
+  removed: bool,
+}
+
+struct FolderWatch {
+  roots: Vec<PathBuf>,
+  options: ScanOptions,
+  _watchers: Vec<RecommendedWatcher>,
+}
+
+/// The folder watchers that follow the most recently scanned roots of each window, keyed by
+/// window label.
+#[derive(Default)]
+pub(crate) struct FolderWatchState {
+  active: Mutex<HashMap<String, FolderWatch>>,
+}
+
+impl FolderWatchState {
+  /// Watches `roots` for the window labelled `window`, replacing what it watched before;
+  /// change events go to that window only and name the root they happened in. The same roots
+  /// with the same options keep the watchers already running.
+  pub(crate) fn watch(
+    &self,
+    app: &tauri::AppHandle,
+    window: &str,
+    roots: &[PathBuf],
+    options: &ScanOptions,
+  ) -> Result<(), String> {
+    let Ok(mut active) = self.active.lock() else {
+      return Err("文件夹监听状态不可用".to_string());
+    };
+    let unchanged = active
+      .get(window)
+      .is_some_and(|watch| watch.roots == roots && watch.options == *options);
+    if unchanged {
+      return Ok(());
+    }
+    // Dropping the previous watchers closes their channels, which ends their worker threads.
+    active.remove(window);
+
+    let mut watchers = Vec::with_capacity(roots.len());
+    for root in roots {
+      let (watcher, receiver) = start_watcher(root, RecursiveMode::Recursive)?;
+
+      let worker_app = app.clone();
+      let worker_window = window.to_string();
+      let worker_root = root.to_path_buf();
+      let worker_options = options.clone();
+      std::thread::Builder::new()
+        .name("rustreader-watch".to_string())
+        .spawn(move || {
+          run_folder_watch(&worker_app, &worker_window, &worker_root, &worker_options, receiver)
+        })
+        .map_err(|error| format!("启动文件夹监听线程失败: {}", error))?;
+      watchers.push(watcher);
+    }
+
+    active.insert(
+      window.to_string(),
+      FolderWatch {
+        roots: roots.to_vec(),
+        options: options.clone(),
+        _watchers: watchers,
+      },
+    );
+    Ok(())
+  }
+
+  pub(crate) fn stop(&self, window: &str) {
+    if let Ok(mut active) = self.active.lock() {
+      active.remove(window);
+    }
+  }
+}
+
+struct FileWatch {
+  path: PathBuf,
+  _watcher: RecommendedWatcher,
+}
+
+/// The watchers for the document each window currently shows in its preview, keyed by window
+/// label.
+#[derive(Default)]
+pub(crate) struct FileWatchState {
+  active: Mutex<HashMap<String, FileWatch>>,
+}
+
+impl FileWatchState {
+  /// Watches `path` for the window labelled `window`; change events report `label`, the path
+  /// as the frontend knows it.
+  pub(crate) fn watch(
+    &self,
+    app: &tauri::AppHandle,
+    window: &str,
+    path: &Path,
+    label: String,
+  ) -> Result<(), String> {
+    let Ok(mut active) = self.active.lock() else {
+      return Err("文件监听状态不可用".to_string());
+    };
+    if active.get(window).is_some_and(|watch| watch.path == path) {
+      return Ok(());
+    }
+    active.remove(window);
+
+    // Editors usually save through a temp file and a rename, which replaces the watched inode,
+    // so follow the parent directory and pick out events for this file name.
+    let Some(parent) = path.parent() else {
+      return Err("无法监听该文件".to_string());
+    };
+    let (watcher, receiver) = start_watcher(parent, RecursiveMode::NonRecursive)?;
+
+    let worker_app = app.clone();
+    let worker_window = window.to_string();
+    let worker_path = path.to_path_buf();
+    std::thread::Builder::new()
+      .name("rustreader-watch-file".to_string())
+      .spawn(move || run_file_watch(&worker_app, &worker_window, &worker_path, label, receiver))
+      .map_err(|error| format!("启动文件监听线程失败: {}", error))?;
+
+    active.insert(
+      window.to_string(),
+      FileWatch {
+        path: path.to_path_buf(),
+        _watcher: watcher,
+      },
+    );
+    Ok(())
+  }
+
+  pub(crate) fn stop(&self, window: &str) {
+    if let Ok(mut active) = self.active.lock() {
+      active.remove(window);
+    }
+  }
+}
+
+type WatchReceiver = Receiver<notify::Result<Event>>;
+
+fn start_watcher(
+  path: &Path,
+  mode: RecursiveMode,
+) -> Result<(RecommendedWatcher, WatchReceiver), String> {
+  let (sender, receiver) = mpsc::channel();
+  let mut watcher = notify::recommended_watcher(move |event| {
+    let _ = sender.send(event);
+  })
+  .map_err(|error| format!("创建文件监听失败: {}", error))?;
+  watcher
+    .watch(path, mode)
+    .map_err(|error| format!("监听路径失败 ({}): {}", path.display(), error))?;
+  Ok((watcher, receiver))
+}
+
+/// Blocks for the next event and gathers whatever follows within `debounce`.
+/// Returns `None` once the watcher has been dropped.
+fn next_event_batch(
+  receiver: &WatchReceiver,
+  debounce: Duration,
+) -> Option<Vec<notify::Result<Event>>> {
+  let first = receiver.recv().ok()?;
+  let mut events = vec![first];
+  let deadline = Instant::now() + debounce;
+  while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+    match receiver.recv_timeout(remaining) {
+      Ok(event) => events.push(event),
+      Err(RecvTimeoutError::Timeout) => break,
+      Err(RecvTimeoutError::Disconnected) => return None,
+    }
+  }
+  Some(events)
+}
+
+fn run_file_watch(
+  app: &tauri::AppHandle,
+  window: &str,
+  path: &Path,
+  path_label: String,
+  receiver: WatchReceiver,
+) {
+  while let Some(events) = next_event_batch(&receiver, FILE_WATCH_DEBOUNCE) {
+    let touched = events.iter().any(|event| match event {
+      Ok(event) => {
+        let is_read = matches!(event.kind, EventKind::Access(kind)
+          if kind != AccessKind::Close(AccessMode::Write));
+        !is_read && event.paths.iter().any(|p| p == path)
+      }
+      Err(error) => {
+        log::warn!("file watch error ({}): {}", path.display(), error);
+        false
+      }
+    });
+    if !touched {
+      continue;
+    }
+
+    let _ = app.emit_to(
+      window,
+      FILE_CHANGED_EVENT,
+      FileChangedEvent {
+        path: path_label.clone(),
+        removed: !path.exists(),
+      },
+    );
+  }
+}
+
+fn run_folder_watch(
+  app: &tauri::AppHandle,
+  window: &str,
+  root: &Path,
+  options: &ScanOptions,
+  receiver: WatchReceiver,
+) {
+  let root_label = root.to_string_lossy().into_owned();
+  while let Some(events) = next_event_batch(&receiver, FOLDER_WATCH_DEBOUNCE) {
+    let mut changes = Vec::new();
+    for event in events {
+      match event {
+        Ok(event) => collect_changes(root, options, event, &mut changes),
+        Err(error) => log::warn!("folder watch error ({}): {}", root.display(), error),
+      }
+    }
+    if changes.is_empty() {
+      continue;
+    }
+
+    let _ = app.emit_to(
+      window,
+      FOLDER_CHANGED_EVENT,
+      FolderChangedEvent {
+        root: root_label.clone(),
+        changes,
+      },
+    );
+  }
+}
+
+/// Keeps `out`, the `index.json` of `root`, current without a window: writes it once, then
+/// rescans whenever files below `root` are added, removed or renamed, and rewrites it when the
+/// listing changed. Only returns on errors.
+pub(crate) fn watch_site_index(root: &Path, out: &Path) -> Result<(), String> {
+  // Watch before the first scan so nothing created while it runs is missed.
+  let (_watcher, receiver) = start_watcher(root, RecursiveMode::Recursive)?;
+  let mut current = regenerate_site_index(root, out, None)?;
+
+  while let Some(events) = next_event_batch(&receiver, SITE_INDEX_DEBOUNCE) {
+    let trigger = events.iter().find_map(|event| site_index_trigger(root, out, event));
+    let Some(trigger) = trigger else {
+      continue;
+    };
+    println!("检测到 {} 的变化，重新生成...", trigger);
+    match regenerate_site_index(root, out, Some(&current)) {
+      Ok(content) => current = content,
+      Err(error) => eprintln!("{}", error),
+    }
+  }
+  Err("文件监听已停止".to_string())
+}
+
+/// Rebuilds the index and writes it unless it equals `previous`; returns the new content.
+fn regenerate_site_index(
+  root: &Path,
+  out: &Path,
+  previous: Option<&str>,
+) -> Result<String, String> {
+  let index = site_index::build_site_index_for(root, out)?;
+  let content = site_index::site_index_json(&index)?;
+  if previous != Some(content.as_str()) {
+    site_index::replace_index_file(out, &content)?;
+    println!("已生成 {}，包含文件数量: {}", out.display(), index.total_files);
+  }
+  Ok(content)
+}
+
+/// The path that makes `event` worth a rescan, skipping the exclusions of `regen-watcher.sh`
+/// (`.git`, any `index.json`, the root `README.md`) and the index being written.
+fn site_index_trigger(root: &Path, out: &Path, event: &notify::Result<Event>) -> Option<String> {
+  let event = match event {
+    Ok(event) => event,
+    Err(error) => {
+      // Lost events (e.g. a queue overflow) leave the index stale; rescan to be safe.
+      eprintln!("文件监听出错 ({}): {}", root.display(), error);
+      return Some(root.display().to_string());
+    }
+  };
+  let structural = matches!(
+    event.kind,
+    EventKind::Any
+      | EventKind::Create(_)
+      | EventKind::Remove(_)
+      | EventKind::Modify(ModifyKind::Name(_) | ModifyKind::Any)
+  );
+  if !structural {
+    return None;
+  }
+
+  let tmp_path = site_index::temp_index_path(out);
+  event.paths.iter().find_map(|path| {
+    if path == out || path == &tmp_path {
+      return None;
+    }
+    if path.file_name().is_some_and(|name| name == site_index::INDEX_FILE_NAME) {
+      return None;
+    }
+    virtual_path_of(root, path).filter(|virtual_path| virtual_path != "README.md")
+  })
+}
+
+fn collect_changes(
+  root: &Path,
+  options: &ScanOptions,
+  event: Event,
+  changes: &mut Vec<FolderChange>,
+) {
+  match event.kind {
+    EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+      for path in &event.paths {
+        push_added(root, options, path, changes);
+      }
+    }
+    EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+      let is_dir = matches!(event.kind, EventKind::Remove(RemoveKind::Folder));
+      for path in &event.paths {
+        push_removed(root, options, path, is_dir, changes);
+      }
+    }
+    EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+      push_renamed(root, options, &event.paths[0], &event.paths[1], changes);
+    }
+    EventKind::Modify(ModifyKind::Name(_)) => {
+      for path in &event.paths {
+        if path.exists() {
+          push_added(root, options, path, changes);
+        } else {
+          push_removed(root, options, path, false, changes);
+        }
+      }
+    }
+    EventKind::Modify(_) | EventKind::Access(AccessKind::Close(AccessMode::Write)) => {
+      for path in &event.paths {
+        if path.is_file() {
+          push_file_change(root, options, "modify", path, None, changes);
+        }
+      }
+    }
+    _ => {}
+  }
+}
+
+/// The virtual path of `path` when the scan that started the watch would have listed it, so
+/// changes below ignored, hidden or too deep folders stay out of the tree.
+fn listed_virtual_path(
+  root: &Path,
+  options: &ScanOptions,
+  path: &Path,
+  is_dir: bool,
+) -> Option<String> {
+  if !scan::is_listed(root, path, is_dir, options) {
+    return None;
+  }
+  virtual_path_of(root, path)
+}
+
+fn virtual_path_of(root: &Path, path: &Path) -> Option<String> {
+  let rel = path.strip_prefix(root).ok()?;
+  if rel.as_os_str().is_empty() {
+    return None;
+  }
+  let in_git_dir = rel
+    .components()
+    .any(|component| matches!(component, Component::Normal(name) if name == ".git"));
+  if in_git_dir {
+    return None;
+  }
+  Some(rel.to_string_lossy().replace('\\', "/"))
+}
+
+/// Categorises a changed file the same way the scan that started the watch did.
+fn file_category(options: &ScanOptions, path: &Path, virtual_path: &str) -> Option<&'static str> {
+  let by_name = categorize_file(virtual_path);
+  if !options.sniff_content {
+    return by_name;
+  }
+  sniff::detect_category(path, by_name).unwrap_or(by_name)
+}
+
+fn push_change(changes: &mut Vec<FolderChange>, change: FolderChange) {
+  // Editors often produce several events per save; keep only the latest one per path.
+  changes.retain(|existing| existing.virtual_path != change.virtual_path);
+  changes.push(change);
+}
+
+fn push_file_change(
+  root: &Path,
+  options: &ScanOptions,
+  kind: &'static str,
+  path: &Path,
+  old_virtual_path: Option<String>,
+  changes: &mut Vec<FolderChange>,
+) {
+  let Some(virtual_path) = listed_virtual_path(root, options, path, false) else {
+    return;
+  };
+  let Some(category) = file_category(options, path, &virtual_path) else {
+    return;
+  };
+
+  let abs_path = path.to_string_lossy().into_owned();
+  let mut file = ScanFile::new(virtual_path.clone(), abs_path, category);
+  if options.sniff_content {
+    file.inspect_container(path);
+  }
+  file.detect_language(path, &file_types::current());
+  if options.collect_metadata {
+    if let Ok(metadata) = std::fs::metadata(path) {
+      file.apply_metadata(&metadata);
+    }
+  }
+  push_change(
+    changes,
+    FolderChange {
+      kind,
+      virtual_path,
+      old_virtual_path,
+      file: Some(file),
+      is_dir: false,
+    },
+  );
+}
+
+fn push_added(root: &Path, options: &ScanOptions, path: &Path, changes: &mut Vec<FolderChange>) {
+  if path.is_dir() {
+    if listed_virtual_path(root, options, path, true).is_none() {
+      return;
+    }
+    for file in scan::scan_new_directory(root, path, options) {
+      push_change(
+        changes,
+        FolderChange {
+          kind: "add",
+          virtual_path: file.virtual_path.clone(),
+          old_virtual_path: None,
+          file: Some(file),
+          is_dir: false,
+        },
+      );
+    }
+    return;
+  }
+  if path.is_file() {
+    push_file_change(root, options, "add", path, None, changes);
+  }
+}
+
+fn push_removed(
+  root: &Path,
+  options: &ScanOptions,
+  path: &Path,
+  is_dir: bool,
+  changes: &mut Vec<FolderChange>,
+) {
+  let Some(virtual_path) = virtual_path_of(root, path) else {
+    return;
+  };
+  // The path is gone, so a name that is not a supported file may have been a directory.
+  let is_dir = is_dir || categorize_file(&virtual_path).is_none();
+  if !scan::is_listed(root, path, is_dir, options) {
+    return;
+  }
+  push_change(
+    changes,
+    FolderChange {
+      kind: "remove",
+      virtual_path,
+      old_virtual_path: None,
+      file: None,
+      is_dir,
+    },
+  );
+}
+
+fn push_renamed(
+  root: &Path,
+  options: &ScanOptions,
+  from: &Path,
+  to: &Path,
+  changes: &mut Vec<FolderChange>,
+) {
+  let is_dir = to.is_dir();
+  let Some(old_virtual_path) = listed_virtual_path(root, options, from, is_dir) else {
+    push_added(root, options, to, changes);
+    return;
+  };
+  let Some(new_virtual_path) = listed_virtual_path(root, options, to, is_dir) else {
+    push_removed(root, options, from, is_dir, changes);
+    return;
+  };
+  if is_dir {
+    // Absolute paths below a moved directory all change, so resend its files under the new name.
+    push_removed(root, options, from, true, changes);
+    push_added(root, options, to, changes);
+    return;
+  }
+
+  if categorize_file(&old_virtual_path).is_some()
+    && file_category(options, to, &new_virtual_path).is_some()
+  {
+    push_file_change(root, options, "rename", to, Some(old_virtual_path), changes);
+  } else {
+    push_removed(root, options, from, false, changes);
+    push_added(root, options, to, changes);
+  }
+}
@@ -0,0 +1,116 @@
+//! Several folders and files opened together. Each root gets a name that is unique within the
+//! workspace, and the virtual paths of its files are moved below that name, so the roots share
+//! one tree with a top-level node each.
+
+use crate::scan::{ScanCounts, ScanFile, ScanOptions, ScanSink};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceRoot {
+  /// The root's top-level node, and the first component of its files' virtual paths.
+  pub name: String,
+  pub path: String,
+}
+
+/// Names `paths` after their last component, numbering repeated names as `docs (2)`, or
+/// `notes (2).md` for files so the extension still identifies them. A path listed twice is
+/// kept once.
+pub fn workspace_roots(paths: &[PathBuf]) -> Vec<WorkspaceRoot> {
+  let mut seen_paths = HashSet::new();
+  let mut names = HashSet::new();
+  let mut roots = Vec::new();
+  for path in paths {
+    if !seen_paths.insert(path) {
+      continue;
+    }
+    let base = base_name(path);
+    let is_file = path.is_file();
+    let mut name = base.clone();
+    let mut number = 2;
+    while !names.insert(name.clone()) {
+      name = numbered_name(&base, number, is_file);
+      number += 1;
+    }
+    roots.push(WorkspaceRoot {
+      name,
+      path: path.to_string_lossy().into_owned(),
+    });
+  }
+  roots
+}
+
+fn base_name(path: &Path) -> String {
+  let name = match path.file_name() {
+    Some(name) => name.to_string_lossy().into_owned(),
+    None => path.display().to_string(),
+  };
+  // A filesystem root has no file name; its display form must not add a level to the tree.
+  let name = name.replace(['/', '\\'], "_").trim_matches('_').to_string();
+  if name.is_empty() {
+    "_".to_string()
+  } else {
+    name
+  }
+}
+
+fn numbered_name(base: &str, number: u32, is_file: bool) -> String {
+  match base.rsplit_once('.').filter(|(stem, _)| is_file && !stem.is_empty()) {
+    Some((stem, extension)) => format!("{stem} ({number}).{extension}"),
+    None => format!("{base} ({number})"),
+  }
+}
+
+/// `virtual_path` moved below the root named `name`. A root that is a single file is listed
+/// under its name alone.
+pub fn prefixed_virtual_path(name: &str, virtual_path: &str) -> String {
+  if virtual_path.is_empty() {
+    name.to_string()
+  } else {
+    format!("{name}/{virtual_path}")
+  }
+}
+
+/// `options` for the next root of a workspace whose earlier roots listed `listed` files in
+/// `elapsed`: `max_files` and `max_duration_ms` become what is left of them, so the limits
+/// apply to the workspace as a whole rather than to each root.
+pub fn remaining_options(options: &ScanOptions, listed: usize, elapsed: Duration) -> ScanOptions {
+  let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+  ScanOptions {
+    max_files: options.max_files.map(|max| max.saturating_sub(listed as u64)),
+    max_duration_ms: options.max_duration_ms.map(|max| max.saturating_sub(elapsed_ms)),
+    ..options.clone()
+  }
+}
+
+pub(crate) fn prefix_files(name: &str, files: &mut [ScanFile]) {
+  for file in files {
+    file.virtual_path = prefixed_virtual_path(name, &file.virtual_path);
+  }
+}
+
+/// Passes the walker's output on to `inner` with every virtual path moved below `name`.
+pub struct PrefixedSink<'a, S: ScanSink> {
+  name: &'a str,
+  inner: &'a S,
+}
+
+impl<'a, S: ScanSink> PrefixedSink<'a, S> {
+  pub fn new(name: &'a str, inner: &'a S) -> Self {
+    Self { name, inner }
+  }
+}
+
+impl<S: ScanSink> ScanSink for PrefixedSink<'_, S> {
+  fn progress(&self, stage: &'static str, counts: ScanCounts, current_path: &Path) {
+    self.inner.progress(stage, counts, current_path);
+  }
+
+  fn files(&self, mut files: Vec<ScanFile>) {
+    prefix_files(self.name, &mut files);
+    self.inner.files(files);
+  }
+}
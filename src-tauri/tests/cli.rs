@@ -0,0 +1,137 @@
+//! Parsing of the process arguments: the forms the desktop app has always accepted, the
+//! subcommands, help, version and usage errors.
+
+use app_lib::cli::{self, Command, ExportArgs, IndexArgs, OpenArgs};
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+fn parse(args: &[&str]) -> Result<Command, String> {
+  cli::parse(args.iter().map(OsString::from))
+}
+
+fn open(args: &[&str]) -> OpenArgs {
+  match parse(args) {
+    Ok(Command::Open(open)) => open,
+    other => panic!("expected desktop arguments for {args:?}, got {other:?}"),
+  }
+}
+
+fn targets(args: &[&str]) -> Vec<String> {
+  open(args).targets
+}
+
+#[test]
+fn accepts_every_open_target_form() {
+  assert!(targets(&[]).is_empty());
+  assert_eq!(targets(&["docs"]), ["docs"]);
+  assert_eq!(targets(&["-o", "docs"]), ["docs"]);
+  assert_eq!(targets(&["--open", "docs"]), ["docs"]);
+  assert_eq!(targets(&["--open=docs"]), ["docs"]);
+  assert_eq!(targets(&["--path", "docs"]), ["docs"]);
+  assert_eq!(targets(&["--path=docs"]), ["docs"]);
+  assert_eq!(targets(&["--", "-dashed"]), ["-dashed"]);
+  assert_eq!(targets(&["-psn_0_12345", "docs"]), ["docs"]);
+  assert_eq!(targets(&["", "  ", "docs"]), ["docs"]);
+  assert!(targets(&["--open="]).is_empty());
+}
+
+#[test]
+fn reads_site_name_and_new_window() {
+  let parsed = open(&["--site-name", "我的站点", "--new-window", "docs"]);
+  assert_eq!(
+    parsed,
+    OpenArgs {
+      targets: vec!["docs".to_string()],
+      site_name: Some("我的站点".to_string()),
+      new_window: true,
+      ..OpenArgs::default()
+    }
+  );
+  assert_eq!(open(&["--site-name=  Docs "]).site_name, Some("Docs".to_string()));
+  assert_eq!(open(&["--site-name="]).site_name, None);
+  assert_eq!(open(&["--", "--site-name=x"]).site_name, None);
+}
+
+#[test]
+fn reads_selection_and_anchor() {
+  let parsed = open(&["docs", "--select", "guide/install.md", "--anchor=#setup"]);
+  assert_eq!(parsed.targets, ["docs"]);
+  assert_eq!(parsed.select.as_deref(), Some("guide/install.md"));
+  assert_eq!(parsed.anchor.as_deref(), Some("setup"));
+  assert_eq!(open(&["--anchor", "#"]).anchor, None);
+  assert!(parse(&["--select"]).unwrap_err().contains("--select"));
+}
+
+#[test]
+fn splits_an_anchor_off_a_target() {
+  assert_eq!(cli::split_anchor("guide.md#install"), Some(("guide.md", "install")));
+  assert_eq!(cli::split_anchor("a#b/guide.md#faq"), Some(("a#b/guide.md", "faq")));
+  assert_eq!(cli::split_anchor("notes#2/a.md"), None);
+  assert_eq!(cli::split_anchor("guide.md#"), None);
+  assert_eq!(cli::split_anchor("#install"), None);
+  assert_eq!(cli::split_anchor("guide.md"), None);
+}
+
+#[test]
+fn keeps_every_target_in_order() {
+  assert_eq!(targets(&["docs/", "specs/", "notes.md"]), ["docs/", "specs/", "notes.md"]);
+  assert_eq!(targets(&["-o", "a", "b", "--path=c", "--", "-d"]), ["a", "b", "c", "-d"]);
+}
+
+#[test]
+fn rejects_unknown_options_with_a_suggestion() {
+  let error = parse(&["--sitename", "x"]).unwrap_err();
+  assert!(error.contains("--sitename"), "{error}");
+  assert!(error.contains("--site-name"), "{error}");
+
+  let error = parse(&["--bogus"]).unwrap_err();
+  assert!(error.contains("--bogus"), "{error}");
+  assert!(parse(&["index", "--output", "x"]).is_err());
+  assert!(parse(&["export", "a", "b", "--name=x"]).is_err());
+}
+
+#[test]
+fn rejects_missing_values_and_extra_paths() {
+  assert!(parse(&["--site-name"]).unwrap_err().contains("--site-name"));
+  assert!(parse(&["-o"]).is_err());
+  assert!(parse(&["index", "--out"]).is_err());
+  assert!(parse(&["index", "a", "b"]).is_err());
+  assert!(parse(&["export", "a"]).is_err());
+  assert!(parse(&["export", "a", "b", "c"]).is_err());
+}
+
+#[test]
+fn prints_help_and_version() {
+  for args in [&["--help"][..], &["-h"], &["help"], &["docs", "--help"]] {
+    assert!(matches!(parse(args), Ok(Command::Help(_))), "{args:?}");
+  }
+  let Ok(Command::Help(index_help)) = parse(&["index", "--help"]) else {
+    panic!("expected index help");
+  };
+  assert!(matches!(parse(&["help", "index"]), Ok(Command::Help(text)) if text == index_help));
+  assert!(parse(&["help", "bogus"]).is_err());
+  assert_eq!(parse(&["--version"]), Ok(Command::Version));
+  assert_eq!(parse(&["-V"]), Ok(Command::Version));
+}
+
+#[test]
+fn parses_subcommands() {
+  assert_eq!(parse(&["index"]), Ok(Command::Index(IndexArgs::default())));
+  assert_eq!(
+    parse(&["watch", "site", "--out=/tmp/index.json"]),
+    Ok(Command::Watch(IndexArgs {
+      root: Some(PathBuf::from("site")),
+      out: Some(PathBuf::from("/tmp/index.json")),
+    }))
+  );
+  assert_eq!(
+    parse(&["export", "--site-name", "内部文档", "src", "dest"]),
+    Ok(Command::Export(ExportArgs {
+      src: PathBuf::from("src"),
+      dest: PathBuf::from("dest"),
+      site_name: Some("内部文档".to_string()),
+    }))
+  );
+  // A folder that happens to share a subcommand's name can still be opened.
+  assert_eq!(targets(&["./index"]), ["./index"]);
+}
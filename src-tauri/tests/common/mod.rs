@@ -0,0 +1,83 @@
+//! Fixtures shared by the integration tests and the scan benchmark.
+
+// Each test crate uses only some of these helpers.
+#![allow(dead_code)]
+
+use app_lib::scan::{self, ScanCounts, ScanFile, ScanOptions, ScanOutcome, ScanSink};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Mutex;
+
+/// A directory below the temp dir that is removed when the fixture is dropped.
+pub struct Fixture(pub PathBuf);
+
+impl Fixture {
+  /// An empty directory named after the test and this process, so parallel runs don't collide.
+  /// The path is canonical, so it compares equal to resolved link targets.
+  pub fn new(name: &str) -> Self {
+    let root = std::env::temp_dir().join(format!("rustreader-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).unwrap();
+    Fixture(root.canonicalize().unwrap())
+  }
+}
+
+impl Drop for Fixture {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_dir_all(&self.0);
+  }
+}
+
+/// A fixture holding `files`, given relative to its root.
+pub fn tree_fixture(name: &str, files: &[&str]) -> Fixture {
+  let fixture = Fixture::new(name);
+  write_tree(&fixture.0, files);
+  fixture
+}
+
+/// Creates `files` below `root`; each holds its own relative path, so copies can be told apart.
+pub fn write_tree(root: &Path, files: &[&str]) {
+  for file in files {
+    let path = root.join(file);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(&path, file.as_bytes()).unwrap();
+  }
+}
+
+#[derive(Default)]
+pub struct CollectSink {
+  files: Mutex<Vec<ScanFile>>,
+}
+
+impl CollectSink {
+  /// The collected files in tree order.
+  pub fn into_files(self) -> Vec<ScanFile> {
+    let mut files = self.files.into_inner().unwrap();
+    scan::sort_scan_files(&mut files);
+    files
+  }
+}
+
+impl ScanSink for CollectSink {
+  fn progress(&self, _stage: &'static str, _counts: ScanCounts, _current_path: &Path) {}
+
+  fn files(&self, files: Vec<ScanFile>) {
+    self.files.lock().unwrap().extend(files);
+  }
+}
+
+/// Walks `root` and returns the outcome with the files it matched, in tree order.
+pub fn walk(root: &Path, options: &ScanOptions, threads: usize) -> (ScanOutcome, Vec<ScanFile>) {
+  let sink = CollectSink::default();
+  let outcome = scan::walk_supported_files(root, options, threads, &AtomicBool::new(false), &sink)
+    .expect("scan was not cancelled");
+  (outcome, sink.into_files())
+}
+
+/// The `virtualPath` of each file, as the UI receives it.
+pub fn virtual_paths(files: &[ScanFile]) -> Vec<String> {
+  files
+    .iter()
+    .map(|file| serde_json::to_value(file).unwrap()["virtualPath"].as_str().unwrap().to_string())
+    .collect()
+}
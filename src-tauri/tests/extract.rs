@@ -0,0 +1,211 @@
+//! Text of Word, Excel, PowerPoint and PDF files, and PDFs that are broken on purpose.
+
+mod common;
+
+use app_lib::extract::extract_text;
+use common::Fixture;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// A zip package holding `parts`.
+fn package(path: &Path, parts: &[(&str, &str)]) {
+  let mut writer = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+  for (name, content) in parts {
+    writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+    writer.write_all(content.as_bytes()).unwrap();
+  }
+  writer.finish().unwrap();
+}
+
+/// `n 0 obj` around a dictionary and, if given, its stream.
+fn pdf_object(pdf: &mut Vec<u8>, id: u32, dict: &str, stream: Option<&[u8]>) {
+  pdf.extend_from_slice(format!("{id} 0 obj\n").as_bytes());
+  match stream {
+    Some(data) => {
+      pdf.extend_from_slice(format!("<< {dict} /Length {} >>\nstream\n", data.len()).as_bytes());
+      pdf.extend_from_slice(data);
+      pdf.extend_from_slice(b"\nendstream\n");
+    }
+    None => pdf.extend_from_slice(format!("<< {dict} >>\n").as_bytes()),
+  }
+  pdf.extend_from_slice(b"endobj\n");
+}
+
+const TO_UNICODE: &str = "\
+/CIDInit /ProcSet findresource begin
+12 dict begin
+begincmap
+1 begincodespacerange
+<0000> <FFFF>
+endcodespacerange
+2 beginbfrange
+<0001> <0002> <0048>
+<0003> <0004> [<6587> <5B57>]
+endbfrange
+endcmap
+end end";
+
+/// One page whose compressed content shows `HI文字` through a Type0 font and its CMap.
+fn one_page_pdf(cmap: &str) -> Vec<u8> {
+  let mut content = ZlibEncoder::new(Vec::new(), Compression::default());
+  content.write_all(b"BT /F1 12 Tf 72 700 Td <0001000200030004> Tj ET").unwrap();
+  let content = content.finish().unwrap();
+
+  let mut pdf = b"%PDF-1.4\n".to_vec();
+  pdf_object(&mut pdf, 1, "/Type /Catalog /Pages 2 0 R", None);
+  pdf_object(&mut pdf, 2, "/Type /Pages /Kids [3 0 R] /Count 1", None);
+  let page = "/Type /Page /Parent 2 0 R /Resources << /Font << /F1 4 0 R >> >> /Contents 5 0 R";
+  pdf_object(&mut pdf, 3, page, None);
+  pdf_object(&mut pdf, 4, "/Type /Font /Subtype /Type0 /ToUnicode 6 0 R", None);
+  pdf_object(&mut pdf, 5, "/Filter /FlateDecode", Some(&content));
+  pdf_object(&mut pdf, 6, "", Some(cmap.as_bytes()));
+  pdf.extend_from_slice(b"trailer\n<< /Root 1 0 R >>\n%%EOF\n");
+  pdf
+}
+
+/// Extracts the PDF `data`, failing the test if that panics or takes longer than a few seconds.
+fn pdf_text(fixture: &Fixture, name: &str, data: &[u8]) -> Option<String> {
+  let path = fixture.0.join(name);
+  std::fs::write(&path, data).unwrap();
+  let (sender, receiver) = mpsc::channel();
+  std::thread::spawn(move || {
+    let _ = sender.send(extract_text(&path, "pdf").unwrap());
+  });
+  match receiver.recv_timeout(Duration::from_secs(5)) {
+    Ok(text) => text,
+    Err(mpsc::RecvTimeoutError::Timeout) => panic!("{name} did not finish"),
+    Err(mpsc::RecvTimeoutError::Disconnected) => panic!("{name} panicked"),
+  }
+}
+
+#[test]
+fn word_paragraphs_become_lines() {
+  let fixture = Fixture::new("extract-word");
+  let path = fixture.0.join("doc.docx");
+  let document = r#"<w:document
+  xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>
+<w:p><w:r><w:t>Hello</w:t></w:r><w:r><w:t xml:space="preserve"> world &amp; more</w:t></w:r></w:p>
+<w:p><w:r><w:t>第二段</w:t></w:r></w:p>
+</w:body></w:document>"#;
+  package(&path, &[("word/document.xml", document)]);
+  let text = extract_text(&path, "word").unwrap();
+  assert_eq!(text.as_deref(), Some("Hello world & more\n第二段\n"));
+}
+
+#[test]
+fn excel_sheets_resolve_shared_strings_and_keep_columns_in_place() {
+  let fixture = Fixture::new("extract-excel");
+  let path = fixture.0.join("book.xlsx");
+  let workbook = r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"
+  xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">
+<sheets><sheet name="Data" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+  let relationships = r#"<Relationships
+  xmlns="http://schemas.openxmlformats.org/package/2006/relationships">
+<Relationship Id="rId1" Type="worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#;
+  let shared = r#"<sst><si><t>名前</t></si><si><r><t>Al</t></r><r><t>ice</t></r></si></sst>"#;
+  let sheet = r#"<worksheet><sheetData>
+<row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1"><v>42</v></c></row>
+<row r="2"><c r="A2" t="s"><v>1</v></c><c r="B2" t="inlineStr"><is><t>inline</t></is></c></row>
+<row r="3"><c r="A3"><v>a</v></c><c r="C3"><v>c</v></c><c r="AB3"><v>ab</v></c></row>
+<row r="4"><c r="B4"><v>b</v></c><c r="XFE4"><v>past the last column</v></c></row>
+</sheetData></worksheet>"#;
+  package(
+    &path,
+    &[
+      ("xl/workbook.xml", workbook),
+      ("xl/_rels/workbook.xml.rels", relationships),
+      ("xl/sharedStrings.xml", shared),
+      ("xl/worksheets/sheet1.xml", sheet),
+    ],
+  );
+  let text = extract_text(&path, "excel").unwrap().unwrap();
+  let gap = "\t".repeat(25);
+  let expected = format!(
+    "Data\n名前\t42\nAlice\tinline\na\t\tc{gap}ab\n\tb\tpast the last column\n\n"
+  );
+  assert_eq!(text, expected);
+}
+
+#[test]
+fn slides_come_in_numeric_order() {
+  let fixture = Fixture::new("extract-slides");
+  let path = fixture.0.join("deck.pptx");
+  let slide = |text: &str| {
+    format!(
+      r#"<p:sld xmlns:p="http://schemas.openxmlformats.org/presentationml/2006/main"
+  xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main"><p:cSld><p:spTree><p:sp>
+<p:txBody><a:p><a:r><a:t>{text}</a:t></a:r></a:p></p:txBody></p:sp></p:spTree></p:cSld></p:sld>"#
+    )
+  };
+  let (one, two, ten) = (slide("One"), slide("Two"), slide("Ten"));
+  package(
+    &path,
+    &[
+      ("ppt/slides/slide10.xml", &ten),
+      ("ppt/slides/slide2.xml", &two),
+      ("ppt/slides/slide1.xml", &one),
+    ],
+  );
+  let text = extract_text(&path, "slides").unwrap();
+  assert_eq!(text.as_deref(), Some("One\n\nTwo\n\nTen\n\n"));
+}
+
+#[test]
+fn office_files_that_are_not_packages_have_no_text() {
+  let fixture = Fixture::new("extract-not-zip");
+  let path = fixture.0.join("fake.docx");
+  std::fs::write(&path, "plain text").unwrap();
+  assert_eq!(extract_text(&path, "word").unwrap(), None);
+}
+
+#[test]
+fn pdf_text_goes_through_flate_and_the_to_unicode_cmap() {
+  let fixture = Fixture::new("extract-pdf");
+  let text = pdf_text(&fixture, "page.pdf", &one_page_pdf(TO_UNICODE));
+  assert_eq!(text.as_deref(), Some("HI文字\n\n"));
+}
+
+#[test]
+fn truncated_pdfs_neither_panic_nor_hang() {
+  let fixture = Fixture::new("extract-pdf-truncated");
+  let pdf = one_page_pdf(TO_UNICODE);
+  for length in b"%PDF-".len()..pdf.len() {
+    pdf_text(&fixture, &format!("cut-{length}.pdf"), &pdf[..length]);
+  }
+}
+
+#[test]
+fn malformed_pdfs_neither_panic_nor_hang() {
+  let fixture = Fixture::new("extract-pdf-garbage");
+  let huge = "99999999999999999999999";
+  let cases = [
+    ("lone-gt.pdf", "1 0 obj\n<< /Type /Page /Contents 2 0 R >".to_string()),
+    ("lone-gt-stream.pdf", "1 0 obj\n<< /Length 3 > stream\nabc".to_string()),
+    ("huge-length.pdf", format!("1 0 obj\n<< /Length {huge} >>\nstream\nabc\nendstream\nendobj")),
+    (
+      "object-stream.pdf",
+      format!(
+        "1 0 obj\n<< /Type /ObjStm /N {huge} /First {huge} /Length 7 >>\nstream\n7 5 8 9\n\
+         endstream\nendobj"
+      ),
+    ),
+    ("nesting.pdf", "[".repeat(10_000)),
+    ("binary.pdf", "\u{ff}".repeat(4096)),
+  ];
+  for (name, body) in cases {
+    let pdf = format!("%PDF-1.4\n{body}");
+    pdf_text(&fixture, name, pdf.as_bytes());
+  }
+}
+
+#[test]
+fn an_empty_codespace_range_does_not_stall_decoding() {
+  let fixture = Fixture::new("extract-pdf-codespace");
+  let cmap = TO_UNICODE.replace("1 begincodespacerange\n", "2 begincodespacerange\n<> <>\n");
+  let text = pdf_text(&fixture, "empty-codespace.pdf", &one_page_pdf(&cmap));
+  assert_eq!(text.as_deref(), Some("HI文字\n\n"));
+}
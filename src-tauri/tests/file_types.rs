@@ -0,0 +1,68 @@
+//! Lookup order of the file-type registry: user globs, suffixes and extensions, then the
+//! built-in names, compound suffixes and extensions.
+
+use app_lib::file_types::{FileTypeRegistry, FileTypeRule};
+
+fn registry(rules: serde_json::Value) -> FileTypeRegistry {
+  let rules: Vec<FileTypeRule> = serde_json::from_value(rules).unwrap();
+  FileTypeRegistry::new(&rules)
+}
+
+#[test]
+fn builtin_rules_cover_extensions_suffixes_and_names() {
+  let registry = registry(serde_json::json!([]));
+  assert_eq!(registry.categorize("docs/Guide.MD"), Some("markdown"));
+  assert_eq!(registry.categorize("docs/map.mm.md"), Some("mindmap"));
+  assert_eq!(registry.categorize("deck.ppt.md"), Some("marpit"));
+  assert_eq!(registry.categorize("src/Dockerfile"), Some("code"));
+  assert_eq!(registry.language("src/Dockerfile", "code"), Some("dockerfile"));
+  assert_eq!(registry.language("src/main.rs", "code"), Some("rust"));
+  assert_eq!(registry.categorize("archive.bin"), None);
+  assert_eq!(registry.categorize(".bashrc"), Some("code"));
+}
+
+#[test]
+fn user_rules_win_over_builtin_ones() {
+  let registry = registry(serde_json::json!([
+    { "extension": ".LOG", "category": "text" },
+    { "extension": "md", "category": "code", "language": "Markdown" },
+    { "suffix": ".notes.txt", "category": "markdown" },
+    { "suffix": ".mm.md", "category": null },
+    { "glob": "CHANGELOG*", "category": "markdown" },
+  ]));
+  assert_eq!(registry.categorize("logs/app.log"), Some("text"));
+  assert_eq!(registry.categorize("README.md"), Some("code"));
+  assert_eq!(registry.language("README.md", "code"), Some("markdown"));
+  assert_eq!(registry.categorize("a/week.notes.txt"), Some("markdown"));
+  assert_eq!(registry.categorize("map.mm.md"), None);
+  assert_eq!(registry.categorize("pkg/changelog"), Some("markdown"));
+}
+
+#[test]
+fn user_suffixes_and_extensions_win_over_builtin_names_and_suffixes() {
+  let registry = registry(serde_json::json!([
+    { "extension": "txt", "category": "text" },
+    { "suffix": "file", "category": null },
+    { "extension": "md", "category": "text" },
+  ]));
+  assert_eq!(registry.categorize("CMakeLists.txt"), Some("text"));
+  assert_eq!(registry.language("CMakeLists.txt", "text"), None);
+  assert_eq!(registry.categorize("src/Dockerfile"), None);
+  assert_eq!(registry.categorize("map.mm.md"), Some("text"));
+  assert_eq!(registry.categorize(".bashrc"), Some("code"));
+  assert_eq!(registry.categorize("main.rs"), Some("code"));
+}
+
+#[test]
+fn path_globs_match_below_the_scanned_root() {
+  let registry = registry(serde_json::json!([
+    { "glob": "docs/**/*.log", "category": "text" },
+    { "glob": "vendor/**", "category": null },
+  ]));
+  assert_eq!(registry.categorize("docs/build.log"), Some("text"));
+  assert_eq!(registry.categorize("docs/a/b/build.log"), Some("text"));
+  assert_eq!(registry.categorize("other/docs/build.log"), None);
+  assert_eq!(registry.categorize("build.log"), None);
+  assert_eq!(registry.categorize("vendor/lib/readme.md"), None);
+  assert_eq!(registry.categorize("src/vendor.md"), Some("markdown"));
+}
@@ -0,0 +1,196 @@
+//! Tokenizing, BM25 ranking and incremental updates of the full-text index.
+
+mod common;
+
+use app_lib::fulltext::{self, FullTextOptions, FullTextState};
+use app_lib::scan::ScanOptions;
+use common::Fixture;
+use std::path::{Path, PathBuf};
+
+fn tokens(text: &str, with_unigrams: bool) -> Vec<String> {
+  let mut tokens = Vec::new();
+  fulltext::tokenize(text, with_unigrams, |token| tokens.push(token));
+  tokens
+}
+
+/// A root holding `files` with the given contents.
+fn content_fixture(name: &str, files: &[(&str, &str)]) -> Fixture {
+  let fixture = Fixture::new(name);
+  for (path, content) in files {
+    std::fs::write(fixture.0.join(path), content).unwrap();
+  }
+  fixture
+}
+
+fn update(state: &FullTextState, root: &Path, complete: bool) {
+  let (_, files) = common::walk(root, &ScanOptions::default(), 2);
+  state.update(root, files, complete).unwrap();
+}
+
+/// The virtual paths of the hits, best first, and the total.
+fn search(state: &FullTextState, root: &Path, query: &str) -> (Vec<String>, u64) {
+  let result = serde_json::to_value(state.search(root, query, 10).unwrap()).unwrap();
+  let hits = result["hits"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .map(|hit| hit["virtualPath"].as_str().unwrap().to_string())
+    .collect();
+  (hits, result["total"].as_u64().unwrap())
+}
+
+/// The single index file below `base`.
+fn index_file(base: &Path) -> PathBuf {
+  let dir = std::fs::read_dir(base).unwrap().next().unwrap().unwrap().path();
+  dir.join("index.jsonl")
+}
+
+#[test]
+fn tokenize_lowercases_words_and_splits_cjk_into_bigrams() {
+  assert_eq!(tokens("Hello, World-42 ÄBC", false), ["hello", "world", "42", "äbc"]);
+  assert_eq!(tokens("全文检索", false), ["全文", "文检", "检索"]);
+  let expected = ["全", "文", "检", "索", "全文", "文检", "检索"];
+  assert_eq!(tokens("全文检索", true), expected);
+}
+
+#[test]
+fn tokenize_separates_mixed_scripts() {
+  assert_eq!(tokens("rust中文search", false), ["rust", "中文", "search"]);
+  // A lone CJK character has no bigram, so it is kept on its own.
+  assert_eq!(tokens("a中b", false), ["a", "中", "b"]);
+  assert_eq!(tokens("カタカナ abc", false), ["カタ", "タカ", "カナ", "abc"]);
+  let blob = "x".repeat(65);
+  assert_eq!(tokens(&format!("short {blob} end"), false), ["short", "end"]);
+}
+
+#[test]
+fn rank_requires_every_term_and_prefers_denser_documents() {
+  let root = content_fixture(
+    "fulltext-rank-root",
+    &[
+      ("dense.md", "rust rust rust guide"),
+      ("sparse.md", "a rust guide among many other words that dilute it"),
+      ("other.md", "python guide"),
+      ("cjk.md", "一个全文检索的例子"),
+    ],
+  );
+  let base = Fixture::new("fulltext-rank-base");
+  let state = FullTextState::new(Some(base.0.clone()));
+  update(&state, &root.0, true);
+
+  let expected = (vec!["dense.md".to_string(), "sparse.md".to_string()], 2);
+  assert_eq!(search(&state, &root.0, "Rust guide"), expected);
+  assert_eq!(search(&state, &root.0, "guide").1, 3);
+  assert_eq!(search(&state, &root.0, "rust missing"), (vec![], 0));
+  assert_eq!(search(&state, &root.0, "检索"), (vec!["cjk.md".into()], 1));
+  assert_eq!(search(&state, &root.0, "检"), (vec!["cjk.md".into()], 1));
+}
+
+#[test]
+fn updates_only_reindex_changed_files_and_persist_as_a_log() {
+  let root = content_fixture(
+    "fulltext-plan-root",
+    &[("a.md", "alpha original"), ("b.md", "beta original"), ("c.md", "gamma")],
+  );
+  let base = Fixture::new("fulltext-plan-base");
+  let state = FullTextState::new(Some(base.0.clone()));
+  update(&state, &root.0, true);
+  assert_eq!(search(&state, &root.0, "original").1, 2);
+
+  // Nothing changed, so the file is left alone.
+  let saved = std::fs::read(index_file(&base.0)).unwrap();
+  update(&state, &root.0, true);
+  assert_eq!(std::fs::read(index_file(&base.0)).unwrap(), saved);
+
+  std::fs::write(root.0.join("a.md"), "alpha rewritten at a new length").unwrap();
+  std::fs::remove_file(root.0.join("b.md")).unwrap();
+  update(&state, &root.0, true);
+  assert_eq!(search(&state, &root.0, "original"), (vec![], 0));
+  assert_eq!(search(&state, &root.0, "rewritten"), (vec!["a.md".into()], 1));
+  // The changes were appended rather than written over the earlier lines.
+  assert!(std::fs::read(index_file(&base.0)).unwrap().starts_with(&saved));
+
+  // A scan that stopped early does not prove missing files are gone.
+  std::fs::write(root.0.join("d.md"), "delta").unwrap();
+  let (_, files) = common::walk(&root.0, &ScanOptions::default(), 2);
+  let partial = files
+    .into_iter()
+    .filter(|file| serde_json::to_value(file).unwrap()["virtualPath"] == "d.md");
+  state.update(&root.0, partial.collect(), false).unwrap();
+  assert_eq!(search(&state, &root.0, "delta").1, 1);
+  assert_eq!(search(&state, &root.0, "gamma").1, 1);
+
+  // Replaying the file gives the same index.
+  let reloaded = FullTextState::new(Some(base.0.clone()));
+  assert_eq!(search(&reloaded, &root.0, "rewritten"), (vec!["a.md".into()], 1));
+  assert_eq!(search(&reloaded, &root.0, "original").1, 0);
+  assert_eq!(search(&reloaded, &root.0, "delta").1, 1);
+  assert_eq!(search(&reloaded, &root.0, "gamma").1, 1);
+}
+
+#[test]
+fn a_broken_index_file_keeps_the_documents_before_it() {
+  let root = content_fixture("fulltext-broken-root", &[("a.md", "alpha"), ("b.md", "beta")]);
+  let base = Fixture::new("fulltext-broken-base");
+  update(&FullTextState::new(Some(base.0.clone())), &root.0, true);
+
+  let file = index_file(&base.0);
+  let mut content = std::fs::read(&file).unwrap();
+  content.extend_from_slice(b"{\"add\":{\"id\":");
+  std::fs::write(&file, content).unwrap();
+
+  let state = FullTextState::new(Some(base.0.clone()));
+  assert_eq!(search(&state, &root.0, "alpha").1, 1);
+  assert_eq!(search(&state, &root.0, "beta").1, 1);
+  // The next update writes the file whole again, without the partial line.
+  std::fs::write(root.0.join("c.md"), "gamma").unwrap();
+  update(&state, &root.0, true);
+  let reloaded = FullTextState::new(Some(base.0.clone()));
+  assert_eq!(search(&reloaded, &root.0, "gamma").1, 1);
+  assert_eq!(search(&reloaded, &root.0, "alpha").1, 1);
+}
+
+#[test]
+fn indexing_stops_at_the_file_and_byte_limits_and_can_be_turned_off() {
+  let root = content_fixture(
+    "fulltext-limits-root",
+    &[("a.md", "shared one"), ("b.md", "shared two"), ("c.md", "shared three")],
+  );
+  let state = FullTextState::new(None);
+  let truncated = |state: &FullTextState| {
+    let result = serde_json::to_value(state.search(&root.0, "shared", 10).unwrap()).unwrap();
+    result["truncated"].as_bool().unwrap()
+  };
+
+  state.set_options(FullTextOptions {
+    max_files: 2,
+    ..FullTextOptions::default()
+  });
+  update(&state, &root.0, true);
+  let expected = vec!["a.md".to_string(), "b.md".to_string()];
+  assert_eq!(search(&state, &root.0, "shared"), (expected, 2));
+  assert!(truncated(&state));
+
+  // `shared one` alone is ten bytes, so the next file no longer fits.
+  state.set_options(FullTextOptions {
+    max_bytes: 10,
+    ..FullTextOptions::default()
+  });
+  update(&state, &root.0, true);
+  assert_eq!(search(&state, &root.0, "shared"), (vec!["a.md".into()], 1));
+
+  state.set_options(FullTextOptions::default());
+  update(&state, &root.0, true);
+  assert_eq!(search(&state, &root.0, "shared").1, 3);
+  assert!(!truncated(&state));
+
+  state.set_options(FullTextOptions {
+    enabled: false,
+    ..FullTextOptions::default()
+  });
+  std::fs::write(root.0.join("d.md"), "shared four").unwrap();
+  update(&state, &root.0, true);
+  assert!(state.search(&root.0, "shared", 10).is_err());
+  state.set_options(FullTextOptions::default());
+  assert_eq!(search(&state, &root.0, "shared").1, 3);
+}
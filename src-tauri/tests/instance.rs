@@ -0,0 +1,44 @@
+//! The single-instance lock: one primary however the invocations interleave.
+
+mod common;
+
+use app_lib::instance::{self, Instance, OpenRequest};
+use common::Fixture;
+use std::path::Path;
+
+fn claim(lock_path: &Path) -> Result<Instance, String> {
+  instance::claim(lock_path, &OpenRequest::default())
+}
+
+#[test]
+fn simultaneous_claims_make_one_primary() {
+  let fixture = Fixture::new("instance-race");
+  let lock_path = fixture.0.join("instance");
+  let claims: Vec<_> = (0..4)
+    .map(|_| {
+      let lock_path = lock_path.clone();
+      std::thread::spawn(move || claim(&lock_path))
+    })
+    .collect();
+  let outcomes: Vec<_> = claims.into_iter().map(|claim| claim.join().unwrap()).collect();
+  let primaries = outcomes
+    .iter()
+    .filter(|outcome| matches!(outcome, Ok(Instance::Primary(_))))
+    .count();
+  assert_eq!(primaries, 1);
+}
+
+#[test]
+fn a_held_lock_is_kept_and_a_released_one_is_taken_over() {
+  let fixture = Fixture::new("instance-twice");
+  let lock_path = fixture.0.join("instance");
+  let first = claim(&lock_path).unwrap();
+  assert!(matches!(first, Instance::Primary(_)));
+  // Nothing serves the first lock here, so the second claim gives up instead of replacing it.
+  assert!(claim(&lock_path).is_err());
+
+  drop(first);
+  // The lock file is left behind, as after a crash.
+  assert!(lock_path.exists());
+  assert!(matches!(claim(&lock_path), Ok(Instance::Primary(_))));
+}
@@ -0,0 +1,68 @@
+//! `max_depth`, `max_files` and `max_duration_ms`, alone and together.
+
+mod common;
+
+use app_lib::scan::{ScanOptions, ScanTruncation};
+use common::tree_fixture;
+
+/// Ten folders, each with a file and a nested folder holding another file.
+fn nested_tree(name: &str) -> common::Fixture {
+  let files: Vec<String> = (0..10)
+    .flat_map(|index| [format!("d{index}/a.md"), format!("d{index}/sub/b.md")])
+    .collect();
+  let files: Vec<&str> = files.iter().map(String::as_str).collect();
+  tree_fixture(name, &files)
+}
+
+#[test]
+fn max_depth_prunes_deeper_folders_and_keeps_walking() {
+  let fixture = nested_tree("limits-depth");
+  let options = ScanOptions {
+    max_depth: Some(1),
+    ..ScanOptions::default()
+  };
+  let (outcome, files) = common::walk(&fixture.0, &options, 2);
+  assert_eq!(outcome.truncated, Some(ScanTruncation::MaxDepth));
+  assert_eq!(files.len(), 10);
+  assert!(common::virtual_paths(&files).iter().all(|path| !path.contains("/sub/")));
+}
+
+#[test]
+fn max_files_stops_the_walk() {
+  let fixture = nested_tree("limits-files");
+  let options = ScanOptions {
+    max_files: Some(3),
+    ..ScanOptions::default()
+  };
+  let (outcome, files) = common::walk(&fixture.0, &options, 2);
+  assert_eq!(outcome.truncated, Some(ScanTruncation::MaxFiles));
+  assert_eq!(files.len(), 3);
+}
+
+#[test]
+fn max_duration_stops_the_walk() {
+  let fixture = nested_tree("limits-duration");
+  let options = ScanOptions {
+    max_duration_ms: Some(0),
+    ..ScanOptions::default()
+  };
+  let (outcome, files) = common::walk(&fixture.0, &options, 2);
+  assert_eq!(outcome.truncated, Some(ScanTruncation::MaxDuration));
+  assert!(files.is_empty());
+}
+
+#[test]
+fn max_files_still_stops_after_a_depth_cut() {
+  let fixture = nested_tree("limits-combined");
+  let options = ScanOptions {
+    max_depth: Some(1),
+    max_files: Some(1),
+    ..ScanOptions::default()
+  };
+  // One worker visits the folders one after the other, so most of them come after the cut.
+  let (outcome, files) = common::walk(&fixture.0, &options, 1);
+  assert_eq!(outcome.truncated, Some(ScanTruncation::MaxFiles));
+  assert_eq!(files.len(), 1);
+  // The root and the folders up to the one whose file no longer fits; the rest are skipped.
+  assert!(outcome.counts.scanned_dirs <= 3, "{:?}", outcome.counts);
+}
@@ -0,0 +1,99 @@
+//! `scan::is_listed` and `scan::scan_new_directory`, which keep watcher deltas to what the scan
+//! listed, agree with the walker.
+
+mod common;
+
+use app_lib::scan::{self, ScanOptions};
+use common::tree_fixture;
+
+const TREE: &[&str] = &[
+  ".gitignore",
+  "a.md",
+  ".hidden.md",
+  ".notes/b.md",
+  "build/out.md",
+  "keep/build.md",
+  "keep/deep/c.md",
+  "keep/deep/deeper/d.md",
+  "node_modules/pkg/readme.md",
+  "node_modules/pkg/.rustreaderignore",
+];
+
+fn assert_agrees(name: &str, options: &ScanOptions) {
+  let fixture = tree_fixture(name, TREE);
+  std::fs::write(fixture.0.join(".gitignore"), "build/\nnode_modules/\n").unwrap();
+  let (_, files) = common::walk(&fixture.0, options, 2);
+  let walked = common::virtual_paths(&files);
+
+  for file in TREE.iter().filter(|file| file.ends_with(".md")) {
+    let listed = scan::is_listed(&fixture.0, &fixture.0.join(file), false, options);
+    assert_eq!(listed, walked.iter().any(|path| path == file), "{file} with {options:?}");
+  }
+}
+
+#[test]
+fn matches_the_walker_with_default_options() {
+  assert_agrees("scan-listed-default", &ScanOptions::default());
+}
+
+#[test]
+fn matches_the_walker_without_hidden_entries_and_with_a_depth_limit() {
+  assert_agrees("scan-listed-hidden", &ScanOptions {
+    include_hidden: false,
+    max_depth: Some(1),
+    ..ScanOptions::default()
+  });
+}
+
+#[test]
+fn matches_the_walker_without_ignore_files() {
+  assert_agrees("scan-listed-unignored", &ScanOptions {
+    respect_ignore_files: false,
+    max_depth: Some(2),
+    ..ScanOptions::default()
+  });
+}
+
+#[test]
+fn rejects_folders_below_the_depth_limit_and_the_root_itself() {
+  let fixture = tree_fixture("scan-listed-dirs", &["keep/deep/c.md"]);
+  let options = ScanOptions {
+    max_depth: Some(1),
+    ..ScanOptions::default()
+  };
+  assert!(scan::is_listed(&fixture.0, &fixture.0.join("keep"), true, &options));
+  assert!(!scan::is_listed(&fixture.0, &fixture.0.join("keep/deep"), true, &options));
+  assert!(!scan::is_listed(&fixture.0, &fixture.0, true, &options));
+  assert!(!scan::is_listed(&fixture.0.join("keep"), &fixture.0.join("a.md"), false, &options));
+}
+
+#[test]
+fn a_directory_created_after_the_scan_keeps_the_ignore_rules_above_it() {
+  let fixture = tree_fixture("scan-listed-new-dir", &["a.md"]);
+  std::fs::write(fixture.0.join(".gitignore"), "build/\n*.tmp.md\n").unwrap();
+  let options = ScanOptions {
+    include_hidden: false,
+    max_depth: Some(2),
+    ..ScanOptions::default()
+  };
+  common::walk(&fixture.0, &options, 2);
+
+  common::write_tree(
+    &fixture.0,
+    &[
+      "new/b.md",
+      "new/c.tmp.md",
+      "new/.d.md",
+      "new/build/e.md",
+      "new/sub/f.md",
+      "new/sub/deep/g.md",
+    ],
+  );
+  let added = scan::scan_new_directory(&fixture.0, &fixture.0.join("new"), &options);
+  assert_eq!(common::virtual_paths(&added), ["new/b.md", "new/sub/f.md"]);
+
+  let (_, files) = common::walk(&fixture.0, &options, 2);
+  let walked = common::virtual_paths(&files);
+  let below: Vec<_> = walked.iter().filter(|path| path.starts_with("new/")).collect();
+  assert_eq!(below, ["new/b.md", "new/sub/f.md"]);
+}
@@ -0,0 +1,31 @@
+//! `collectMetadata`: size and times on every listed file, or none at all.
+
+mod common;
+
+use app_lib::scan::ScanOptions;
+use common::tree_fixture;
+
+fn metadata_fields(options: &ScanOptions) -> Vec<(Option<u64>, bool)> {
+  let fixture = tree_fixture("metadata", &["a.md", "sub/bb.md"]);
+  let (_, files) = common::walk(&fixture.0, options, 2);
+  files
+    .iter()
+    .map(|file| {
+      let value = serde_json::to_value(file).unwrap();
+      (value["size"].as_u64(), value.get("modifiedMs").is_some())
+    })
+    .collect()
+}
+
+#[test]
+fn metadata_is_collected_by_default_and_can_be_turned_off() {
+  // Each fixture file holds its own relative path.
+  let fields = metadata_fields(&ScanOptions::default());
+  assert_eq!(fields, [(Some(4), true), (Some(9), true)]);
+  let options = ScanOptions {
+    collect_metadata: false,
+    ..ScanOptions::default()
+  };
+  let fields = metadata_fields(&options);
+  assert_eq!(fields, [(None, false), (None, false)]);
+}
@@ -0,0 +1,104 @@
+//! Office packages: sniffing scans read the manifest for the viewer and for macros, plain
+//! scans go by the name alone.
+
+mod common;
+
+use app_lib::scan::ScanOptions;
+use common::Fixture;
+use std::io::Write;
+use std::path::Path;
+
+/// A zip package with a manifest naming `main_type` as the main part, plus `parts`.
+fn package(path: &Path, main_type: &str, parts: &[&str]) {
+  let content_types = format!(
+    r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+<Override PartName="/main.xml" ContentType="application/{main_type}.main+xml"/></Types>"#
+  );
+  let mut writer = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+  let options = zip::write::SimpleFileOptions::default();
+  writer.start_file("[Content_Types].xml", options).unwrap();
+  writer.write_all(content_types.as_bytes()).unwrap();
+  for part in parts {
+    writer.start_file(*part, options).unwrap();
+    writer.write_all(b"part").unwrap();
+  }
+  writer.finish().unwrap();
+}
+
+/// `(virtualPath, category, hasMacros)` of each listed file.
+fn listed(root: &Path, sniff_content: bool) -> Vec<(String, String, bool)> {
+  let options = ScanOptions {
+    sniff_content,
+    ..ScanOptions::default()
+  };
+  let (_, files) = common::walk(root, &options, 2);
+  files
+    .iter()
+    .map(|file| {
+      let file = serde_json::to_value(file).unwrap();
+      (
+        file["virtualPath"].as_str().unwrap().to_string(),
+        file["category"].as_str().unwrap().to_string(),
+        file["hasMacros"].as_bool().unwrap_or(false),
+      )
+    })
+    .collect()
+}
+
+fn office_fixture(name: &str) -> Fixture {
+  let fixture = Fixture::new(name);
+  package(
+    &fixture.0.join("macros.docm"),
+    "vnd.ms-word.document.macroenabled",
+    &["word/document.xml", "word/vbaProject.bin"],
+  );
+  package(
+    &fixture.0.join("cleaned.docm"),
+    "vnd.ms-word.document.macroenabled",
+    &["word/document.xml"],
+  );
+  package(
+    &fixture.0.join("show.xltm"),
+    "vnd.ms-powerpoint.slideshow.macroenabled",
+    &["ppt/presentation.xml", "ppt/vbaProject.bin"],
+  );
+  package(
+    &fixture.0.join("template.dotx"),
+    "vnd.openxmlformats-officedocument.wordprocessingml.template",
+    &["word/document.xml"],
+  );
+  fixture
+}
+
+fn entry(path: &str, category: &str, has_macros: bool) -> (String, String, bool) {
+  (path.to_string(), category.to_string(), has_macros)
+}
+
+#[test]
+fn sniffing_scans_read_the_manifest_for_variants_and_macros() {
+  let fixture = office_fixture("scan-office-sniffed");
+  assert_eq!(
+    listed(&fixture.0, true),
+    [
+      entry("cleaned.docm", "word", false),
+      entry("macros.docm", "word", true),
+      entry("show.xltm", "slides", true),
+      entry("template.dotx", "word", false),
+    ]
+  );
+}
+
+#[test]
+fn plain_scans_go_by_the_name_and_leave_packages_closed() {
+  let fixture = office_fixture("scan-office-plain");
+  assert_eq!(
+    listed(&fixture.0, false),
+    [
+      entry("cleaned.docm", "word", false),
+      entry("macros.docm", "word", false),
+      entry("show.xltm", "excel", false),
+      entry("template.dotx", "word", false),
+    ]
+  );
+}
@@ -0,0 +1,57 @@
+#![cfg(unix)]
+
+mod common;
+
+use app_lib::scan::ScanOptions;
+use common::Fixture;
+use std::os::unix::fs::symlink;
+use std::path::Path;
+
+/// root/docs/a.md, root/docs/loop -> root, root/shared -> docs, root/alias.md -> docs/a.md
+fn symlink_fixture(name: &str) -> Fixture {
+  let fixture = Fixture::new(name);
+  let root = &fixture.0;
+  std::fs::create_dir_all(root.join("docs")).unwrap();
+  std::fs::write(root.join("docs/a.md"), "# a").unwrap();
+  symlink(root, root.join("docs/loop")).unwrap();
+  symlink(root.join("docs"), root.join("shared")).unwrap();
+  symlink(root.join("docs/a.md"), root.join("alias.md")).unwrap();
+  fixture
+}
+
+fn scan(root: &Path, follow_symlinks: bool) -> Vec<serde_json::Value> {
+  let options = ScanOptions {
+    follow_symlinks,
+    ..ScanOptions::default()
+  };
+  let (_, files) = common::walk(root, &options, 4);
+  files
+    .iter()
+    .map(|file| serde_json::to_value(file).unwrap())
+    .collect()
+}
+
+fn virtual_paths(files: &[serde_json::Value]) -> Vec<&str> {
+  files
+    .iter()
+    .map(|file| file["virtualPath"].as_str().unwrap())
+    .collect()
+}
+
+#[test]
+fn symlinks_are_skipped_by_default() {
+  let fixture = symlink_fixture("symlinks-default");
+  let files = scan(&fixture.0, false);
+  assert_eq!(virtual_paths(&files), ["docs/a.md"]);
+}
+
+#[test]
+fn following_symlinks_terminates_on_loops() {
+  let fixture = symlink_fixture("symlinks-follow");
+  let files = scan(&fixture.0, true);
+  assert_eq!(virtual_paths(&files), ["alias.md", "docs/a.md", "shared/a.md"]);
+
+  let target = fixture.0.join("docs/a.md");
+  assert_eq!(files[0]["linkTarget"].as_str(), Some(target.to_str().unwrap()));
+  assert!(files[1].get("linkTarget").is_none());
+}
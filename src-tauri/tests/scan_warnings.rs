@@ -0,0 +1,55 @@
+//! Paths the walker cannot read: reported as warnings and counted as skipped.
+#![cfg(unix)]
+
+mod common;
+
+use app_lib::scan::ScanOptions;
+use common::tree_fixture;
+use std::os::unix::fs::{symlink, PermissionsExt};
+
+#[test]
+fn an_unreadable_directory_is_one_warning_and_one_skip() {
+  let fixture = tree_fixture("warnings-unreadable", &["a.md", "locked/b.md"]);
+  let locked = fixture.0.join("locked");
+  std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o000)).unwrap();
+  // Privileged users read the directory anyway, so there is nothing to report.
+  let readable = std::fs::read_dir(&locked).is_ok();
+  let (outcome, files) = common::walk(&fixture.0, &ScanOptions::default(), 2);
+  std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+  if readable {
+    return;
+  }
+
+  assert_eq!(common::virtual_paths(&files), ["a.md"]);
+  assert_eq!(outcome.counts.skipped, 1);
+  assert_eq!(outcome.warnings.len(), 1);
+  assert_eq!(outcome.warnings[0].path, locked.to_string_lossy());
+  assert_eq!(outcome.warnings[0].kind, "PermissionDenied");
+}
+
+#[test]
+fn a_broken_link_that_is_followed_is_one_warning_and_one_skip() {
+  let fixture = tree_fixture("warnings-broken-link", &["a.md"]);
+  let link = fixture.0.join("gone.md");
+  symlink(fixture.0.join("missing.md"), &link).unwrap();
+  let options = ScanOptions {
+    follow_symlinks: true,
+    ..ScanOptions::default()
+  };
+  let (outcome, files) = common::walk(&fixture.0, &options, 2);
+
+  assert_eq!(common::virtual_paths(&files), ["a.md"]);
+  assert_eq!(outcome.counts.skipped, 1);
+  assert_eq!(outcome.warnings.len(), 1);
+  assert_eq!(outcome.warnings[0].path, link.to_string_lossy());
+  assert_eq!(outcome.warnings[0].kind, "NotFound");
+}
+
+#[test]
+fn a_readable_tree_has_no_warnings() {
+  let fixture = tree_fixture("warnings-none", &["a.md", "sub/b.md"]);
+  let (outcome, files) = common::walk(&fixture.0, &ScanOptions::default(), 2);
+  assert_eq!(files.len(), 2);
+  assert_eq!(outcome.counts.skipped, 0);
+  assert!(outcome.warnings.is_empty());
+}
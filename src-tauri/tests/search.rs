@@ -0,0 +1,114 @@
+//! Content search: literal and regex queries, case folding, binary files and snippet offsets.
+
+mod common;
+
+use app_lib::scan::ScanOptions;
+use app_lib::search::{ContentMatcher, SearchOptions};
+use common::Fixture;
+
+/// `(lineNumber, snippet, matchStart, matchEnd)` of each match.
+type Hit = (u64, String, u64, u64);
+
+/// Searches `file` below `fixture` for `query`; `None` when the file was skipped.
+fn search(
+  fixture: &Fixture,
+  file: &str,
+  query: &str,
+  options: serde_json::Value,
+) -> Result<Option<Vec<Hit>>, String> {
+  let options: SearchOptions = serde_json::from_value(options).unwrap();
+  let matcher = ContentMatcher::new(query, &options)?;
+  let (_, files) = common::walk(&fixture.0, &ScanOptions::default(), 1);
+  let file = files
+    .iter()
+    .find(|scanned| serde_json::to_value(scanned).unwrap()["virtualPath"] == file)
+    .unwrap();
+  let Some(matches) = matcher.search_file(file, 100).unwrap() else {
+    return Ok(None);
+  };
+  let hits = matches
+    .iter()
+    .map(|found| {
+      let found = serde_json::to_value(found).unwrap();
+      (
+        found["lineNumber"].as_u64().unwrap(),
+        found["snippet"].as_str().unwrap().to_string(),
+        found["matchStart"].as_u64().unwrap(),
+        found["matchEnd"].as_u64().unwrap(),
+      )
+    })
+    .collect();
+  Ok(Some(hits))
+}
+
+fn lines(hits: Result<Option<Vec<Hit>>, String>) -> Vec<u64> {
+  hits.unwrap().unwrap().into_iter().map(|hit| hit.0).collect()
+}
+
+fn write(fixture: &Fixture, name: &str, content: impl AsRef<[u8]>) {
+  std::fs::write(fixture.0.join(name), content).unwrap();
+}
+
+#[test]
+fn literal_queries_escape_regex_syntax() {
+  let fixture = Fixture::new("search-escape");
+  write(&fixture, "notes.md", "axb\na.b\ncost (USD) $5\n[x]+\n");
+  let literal = serde_json::json!({});
+  let regex = serde_json::json!({ "regex": true });
+
+  assert_eq!(lines(search(&fixture, "notes.md", "a.b", literal.clone())), [2]);
+  assert_eq!(lines(search(&fixture, "notes.md", "a.b", regex.clone())), [1, 2]);
+  assert_eq!(lines(search(&fixture, "notes.md", "(USD) $5", literal.clone())), [3]);
+  assert_eq!(lines(search(&fixture, "notes.md", "[x]+", literal.clone())), [4]);
+  assert_eq!(lines(search(&fixture, "notes.md", r"^a\.b$", regex.clone())), [2]);
+  // Patterns that match the empty string do not flag every line.
+  assert_eq!(lines(search(&fixture, "notes.md", "z*", regex.clone())), Vec::<u64>::new());
+  assert!(search(&fixture, "notes.md", "(USD", regex).is_err());
+}
+
+#[test]
+fn queries_ignore_case_unless_asked_not_to() {
+  let fixture = Fixture::new("search-case");
+  write(&fixture, "case.txt", "Hello\nHELLO\nhello\nÄPFEL und Äpfel\n");
+  let folded = serde_json::json!({});
+  let exact = serde_json::json!({ "caseSensitive": true });
+
+  assert_eq!(lines(search(&fixture, "case.txt", "hello", folded.clone())), [1, 2, 3]);
+  assert_eq!(lines(search(&fixture, "case.txt", "hello", exact.clone())), [3]);
+  assert_eq!(lines(search(&fixture, "case.txt", "äpfel", folded)), [4]);
+  assert_eq!(lines(search(&fixture, "case.txt", "äpfel", exact)), Vec::<u64>::new());
+}
+
+#[test]
+fn a_nul_in_the_head_skips_the_file_as_binary() {
+  let fixture = Fixture::new("search-binary");
+  write(&fixture, "head.txt", b"needle\0needle\n");
+  let mut late = "needle\n".repeat(2000).into_bytes();
+  late.push(0);
+  write(&fixture, "late.txt", &late);
+
+  assert_eq!(search(&fixture, "head.txt", "needle", serde_json::json!({})), Ok(None));
+  // A NUL beyond the first 8 KiB does not count.
+  let hits = lines(search(&fixture, "late.txt", "needle", serde_json::json!({})));
+  assert_eq!(hits.len(), 100);
+}
+
+#[test]
+fn snippet_offsets_count_utf16_code_units() {
+  let fixture = Fixture::new("search-utf16");
+  let long_prefix = "字".repeat(100);
+  write(
+    &fixture,
+    "cjk.md",
+    format!("  中文😀 find me  \n{long_prefix}find\n😀😀find{}\n", "尾".repeat(100)),
+  );
+  let hits = search(&fixture, "cjk.md", "find", serde_json::json!({})).unwrap().unwrap();
+
+  // `中文😀 ` is two CJK characters, a surrogate pair and a space; the indent is trimmed.
+  assert_eq!(hits[0], (1, "中文😀 find me".to_string(), 5, 9));
+  // Context before the match is cut to 80 characters after an ellipsis.
+  let expected = format!("…{}find", "字".repeat(80));
+  assert_eq!(hits[1], (2, expected, 81, 85));
+  let expected = format!("😀😀find{}…", "尾".repeat(80));
+  assert_eq!(hits[2], (3, expected, 4, 8));
+}
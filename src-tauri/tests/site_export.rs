@@ -0,0 +1,68 @@
+mod common;
+
+use app_lib::site_export;
+use common::Fixture;
+
+const PAGE: &str = "<html><head><title>文件浏览器</title></head>\
+<body><span class=\"brand\" id=\"brandText\">文件浏览器</span></body></html>";
+
+/// `files` below `src/`, each holding its own path.
+fn fixture(name: &str, files: &[&str]) -> Fixture {
+  let fixture = Fixture::new(name);
+  common::write_tree(&fixture.0.join("src"), files);
+  fixture
+}
+
+fn ui_files() -> Vec<(String, Vec<u8>)> {
+  vec![
+    ("index.html".to_string(), PAGE.as_bytes().to_vec()),
+    ("assets/app.js".to_string(), b"// ui".to_vec()),
+  ]
+}
+
+#[test]
+fn exports_ui_files_and_index() {
+  let fixture = fixture("export-site", &["a.md", "docs/b.pdf", "data.csv", "assets/app.js"]);
+  let (src, dest) = (fixture.0.join("src"), fixture.0.join("site"));
+  let summary = site_export::export_site(&src, &dest, Some("Team <Docs>"), &ui_files()).unwrap();
+
+  assert_eq!(summary.files, 2);
+  assert_eq!(summary.skipped, ["assets/app.js"]);
+  assert_eq!(std::fs::read_to_string(dest.join("docs/b.pdf")).unwrap(), "docs/b.pdf");
+  assert_eq!(std::fs::read_to_string(dest.join("assets/app.js")).unwrap(), "// ui");
+  assert!(!dest.join("data.csv").exists());
+
+  let page = std::fs::read_to_string(dest.join("index.html")).unwrap();
+  assert!(page.contains("<title>Team &lt;Docs&gt;</title>"));
+  assert!(page.contains("id=\"brandText\">Team &lt;Docs&gt;</span>"));
+
+  let index = std::fs::read_to_string(dest.join("index.json")).unwrap();
+  let index: serde_json::Value = serde_json::from_str(&index).unwrap();
+  assert_eq!(index["totalFiles"], 2);
+  let listed: Vec<&str> = index["groups"]
+    .as_array()
+    .unwrap()
+    .iter()
+    .flat_map(|group| group["categories"].as_array().unwrap())
+    .flat_map(|category| category["files"].as_array().unwrap())
+    .map(|file| file.as_str().unwrap())
+    .collect();
+  assert_eq!(listed, ["a.md", "docs/b.pdf"]);
+}
+
+#[test]
+fn refuses_a_non_empty_destination() {
+  let fixture = fixture("export-non-empty", &["a.md"]);
+  std::fs::create_dir_all(fixture.0.join("site")).unwrap();
+  std::fs::write(fixture.0.join("site/keep.txt"), "").unwrap();
+  let (src, dest) = (fixture.0.join("src"), fixture.0.join("site"));
+  assert!(site_export::export_site(&src, &dest, None, &ui_files()).is_err());
+}
+
+#[test]
+fn site_name_drops_the_app_prefix_and_defaults() {
+  let page = site_export::apply_site_name(PAGE, "RustReader - 周报");
+  assert!(page.contains("<title>周报</title>") && page.contains(">周报</span>"));
+  let page = site_export::apply_site_name(PAGE, "  ");
+  assert_eq!(page, PAGE);
+}
@@ -0,0 +1,141 @@
+//! `site_index` output compared byte for byte with `index.json` files written by
+//! `ui/generate.py`. To refresh a golden file, build the same tree, run
+//! `python3 ui/generate.py <tree>` and copy the `index.json` it writes.
+
+mod common;
+
+use app_lib::site_index;
+use common::tree_fixture;
+use std::io::Write;
+use std::path::Path;
+
+const MIXED_TREE: &[&str] = &[
+  "README.md",
+  "Zeta.md",
+  "alpha.md",
+  "notes.txt",
+  "Dockerfile",
+  "diagram.drawio",
+  "data.csv",
+  "archive.bin",
+  ".gitignore",
+  ".hidden/secret.md",
+  ".git/HEAD",
+  ".git/notes.md",
+  "ignored/kept.md",
+  "src/main.rs",
+  "src/LIB.RS",
+  "src/Makefile",
+  "docs/guide.md",
+  "docs/map.mm.md",
+  "docs/deck.ppt.md",
+  "docs/a b/report.pdf",
+  "docs/a b/budget.xlsx",
+  "docs/a b/plan.docx",
+  "docs/a b/talk.pptx",
+  "docs/图片/封面.PNG",
+  "docs/图片/b.jpg",
+  "media/clip.mp4",
+  "media/song.mp3",
+  "a-b/x.md",
+  "a/z.md",
+  "a/b/y.md",
+  "café/crème.md",
+];
+
+fn site_index_json(root: &Path) -> String {
+  let index = site_index::build_site_index(root).unwrap();
+  site_index::site_index_json(&index).unwrap()
+}
+
+/// Writes a zip at `path` whose manifest makes it a workbook, whatever its name says.
+fn workbook_package(path: &Path) {
+  let content_types = r#"<Types
+  xmlns="http://schemas.openxmlformats.org/package/2006/content-types">
+<Override PartName="/xl/workbook.xml"
+  ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>
+</Types>"#;
+  let mut writer = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+  writer
+    .start_file("[Content_Types].xml", zip::write::SimpleFileOptions::default())
+    .unwrap();
+  writer.write_all(content_types.as_bytes()).unwrap();
+  writer.finish().unwrap();
+}
+
+// Creating symlinks takes extra privileges on Windows.
+#[cfg(unix)]
+#[test]
+fn mixed_tree_matches_generate_py() {
+  use std::os::unix::fs::symlink;
+
+  let fixture = tree_fixture("site-index-mixed", MIXED_TREE);
+  std::fs::write(fixture.0.join(".gitignore"), "ignored/\n").unwrap();
+  workbook_package(&fixture.0.join("docs/a b/mislabelled.docx"));
+  // Linked files are listed, linked directories and broken links are not.
+  symlink(fixture.0.join("alpha.md"), fixture.0.join("linked.md")).unwrap();
+  symlink(fixture.0.join("docs"), fixture.0.join("linked-docs")).unwrap();
+  symlink(fixture.0.join("missing.md"), fixture.0.join("broken.md")).unwrap();
+  assert_eq!(site_index_json(&fixture.0), include_str!("golden/site_index_mixed.json"));
+}
+
+#[test]
+fn empty_tree_matches_generate_py() {
+  let fixture = tree_fixture("site-index-empty", &["data.csv"]);
+  assert_eq!(site_index_json(&fixture.0), include_str!("golden/site_index_empty.json"));
+}
+
+#[test]
+fn writes_index_json_into_the_root_by_default() {
+  let fixture = tree_fixture("site-index-write", &["a.md", "b/c.pdf"]);
+  let expected = site_index_json(&fixture.0);
+  let (out, total_files) = site_index::write_site_index(&fixture.0, None).unwrap();
+  assert_eq!(out, fixture.0.join(site_index::INDEX_FILE_NAME));
+  assert_eq!(total_files, 2);
+  assert_eq!(std::fs::read_to_string(out).unwrap(), expected);
+}
+
+#[test]
+fn rejects_missing_and_file_roots() {
+  let fixture = tree_fixture("site-index-errors", &["a.md"]);
+  assert!(site_index::build_site_index(&fixture.0.join("missing")).is_err());
+  assert!(site_index::build_site_index(&fixture.0.join("a.md")).is_err());
+}
+
+#[test]
+fn leaves_out_the_sites_own_files() {
+  let fixture = tree_fixture(
+    "site-index-own-files",
+    &[
+      "index.html",
+      "generate.py",
+      "assets/app.js",
+      "assets/styles.css",
+      "assets/fonts/logo.png",
+      "guide.md",
+      "docs/index.html",
+      "docs/assets/chart.png",
+      "docs/index.json",
+    ],
+  );
+  let expected = site_index_json(&fixture.0);
+  let (_, total_files) = site_index::write_site_index(&fixture.0, None).unwrap();
+  assert_eq!(total_files, 4);
+  // A second run must not pick up the index the first one wrote.
+  assert_eq!(site_index_json(&fixture.0), expected);
+  assert!(!expected.contains("\"index.json\"") && !expected.contains("assets/app.js"));
+  assert!(expected.contains("docs/assets/chart.png") && expected.contains("docs/index.json"));
+}
+
+#[test]
+fn an_out_file_inside_the_root_is_not_listed() {
+  let fixture = tree_fixture("site-index-out", &["guide.md", "site/keep.md"]);
+  let out = fixture.0.join("site/listing.json");
+  let (_, first_total) = site_index::write_site_index(&fixture.0, Some(&out)).unwrap();
+  let first = std::fs::read_to_string(&out).unwrap();
+  // Regenerating must not pick up the listing the first run wrote.
+  let (_, second_total) = site_index::write_site_index(&fixture.0, Some(&out)).unwrap();
+  assert_eq!((first_total, second_total), (2, 2));
+  assert_eq!(std::fs::read_to_string(&out).unwrap(), first);
+  assert!(!first.contains("listing.json"));
+}
@@ -0,0 +1,79 @@
+//! Content sniffing: binary signatures, MPEG-4 brands, and extensionless text and scripts.
+
+mod common;
+
+use app_lib::file_types::FileTypeRegistry;
+use app_lib::sniff;
+use common::Fixture;
+
+/// Writes `content` to `name` and sniffs it, starting from the category its name gives.
+fn sniff(fixture: &Fixture, name: &str, content: &[u8]) -> Option<&'static str> {
+  let path = fixture.0.join(name);
+  std::fs::write(&path, content).unwrap();
+  let by_name = FileTypeRegistry::new(&[]).categorize(name);
+  sniff::detect_category(&path, by_name).unwrap()
+}
+
+/// An ISO base media header with the major brand `brand`.
+fn ftyp(brand: &[u8; 4]) -> Vec<u8> {
+  let mut head = b"\0\0\0\x18ftyp".to_vec();
+  head.extend_from_slice(brand);
+  head.extend_from_slice(b"\0\0\0\0isommp42");
+  head
+}
+
+#[test]
+fn signatures_win_over_the_name() {
+  let fixture = Fixture::new("sniff-signatures");
+  assert_eq!(sniff(&fixture, "photo.txt", b"\x89PNG\r\n\x1a\n\0\0"), Some("images"));
+  assert_eq!(sniff(&fixture, "photo", b"\xff\xd8\xff\xe0"), Some("images"));
+  assert_eq!(sniff(&fixture, "anim.md", b"GIF89a\x01\0"), Some("images"));
+  assert_eq!(sniff(&fixture, "pic", b"RIFF\0\0\0\0WEBPVP8 "), Some("images"));
+  assert_eq!(sniff(&fixture, "paper.txt", b"%PDF-1.7\n"), Some("pdf"));
+  assert_eq!(sniff(&fixture, "song.txt", b"fLaC\0\0\0\x22"), Some("audio"));
+  assert_eq!(sniff(&fixture, "tagged", b"ID3\x04\0\0"), Some("audio"));
+  assert_eq!(sniff(&fixture, "voice", b"OggS\0\x02\x01vorbis"), Some("audio"));
+  assert_eq!(sniff(&fixture, "clip.ogg", b"OggS\0\x02\x80theora"), Some("video"));
+  assert_eq!(sniff(&fixture, "frame", b"\xff\xfb\x90\x64\0\0"), Some("audio"));
+  // A frame sync alone does not overrule a name.
+  assert_eq!(sniff(&fixture, "notes.txt", b"\xff\xfb\x90\x64\0\0"), Some("text"));
+}
+
+#[test]
+fn mpeg4_brands_keep_audio_and_video_names_and_spot_heif_images() {
+  let fixture = Fixture::new("sniff-ftyp");
+  assert_eq!(sniff(&fixture, "song.m4a", &ftyp(b"isom")), Some("audio"));
+  assert_eq!(sniff(&fixture, "song2.m4a", &ftyp(b"mp42")), Some("audio"));
+  assert_eq!(sniff(&fixture, "song3.m4a", &ftyp(b"3gp4")), Some("audio"));
+  assert_eq!(sniff(&fixture, "movie.mp4", &ftyp(b"M4A ")), Some("video"));
+  assert_eq!(sniff(&fixture, "movie.txt", &ftyp(b"isom")), Some("video"));
+  assert_eq!(sniff(&fixture, "book", &ftyp(b"M4B ")), Some("audio"));
+  assert_eq!(sniff(&fixture, "camera", &ftyp(b"heic")), Some("images"));
+  assert_eq!(sniff(&fixture, "still.mp4", &ftyp(b"mif1")), Some("images"));
+  assert_eq!(sniff(&fixture, "modern", &ftyp(b"avif")), Some("images"));
+}
+
+#[test]
+fn extensionless_files_that_read_as_text_are_text() {
+  let fixture = Fixture::new("sniff-text");
+  assert_eq!(sniff(&fixture, "LICENSE", "MIT License\n\n版权所有\n".as_bytes()), Some("text"));
+  assert_eq!(sniff(&fixture, "blob", b"text\0with a nul"), None);
+  assert_eq!(sniff(&fixture, "noise", b"\x01\x02\x03\x04\x05\x06 mostly control"), None);
+  assert_eq!(sniff(&fixture, "latin1", b"caf\xe9 au lait"), None);
+  // Only extensionless files go through the text heuristic.
+  assert_eq!(sniff(&fixture, "data.unknown", b"plain words"), None);
+  assert_eq!(sniff(&fixture, "empty", b""), None);
+}
+
+#[test]
+fn extensionless_scripts_are_code_in_the_shebang_language() {
+  let fixture = Fixture::new("sniff-shebang");
+  assert_eq!(sniff(&fixture, "deploy", b"#!/usr/bin/env python3\nprint(1)\n"), Some("code"));
+  assert_eq!(sniff(&fixture, "build.unknown", b"#!/bin/sh\necho hi\n"), Some("code"));
+  assert_eq!(sniff(&fixture, "run", b"#!/usr/bin/env -S node --trace\n"), Some("code"));
+  assert_eq!(sniff::shebang_language_of(&fixture.0.join("deploy")), Some("python"));
+  assert_eq!(sniff::shebang_language_of(&fixture.0.join("build.unknown")), Some("bash"));
+  assert_eq!(sniff::shebang_language_of(&fixture.0.join("run")), Some("javascript"));
+  assert_eq!(sniff(&fixture, "README", b"# Not a script\n"), Some("text"));
+  assert_eq!(sniff::shebang_language_of(&fixture.0.join("README")), None);
+}
@@ -0,0 +1,82 @@
+//! Root names and virtual path namespaces of a workspace opened from several paths.
+
+mod common;
+
+use app_lib::scan::{self, ScanOptions, ScanTruncation};
+use app_lib::workspace::{self, PrefixedSink};
+use common::{tree_fixture, CollectSink};
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+fn names(paths: &[PathBuf]) -> Vec<String> {
+  workspace::workspace_roots(paths).into_iter().map(|root| root.name).collect()
+}
+
+#[test]
+fn names_roots_after_their_last_component() {
+  let fixture = tree_fixture(
+    "workspace-names",
+    &["a/docs/x.md", "b/docs/y.md", "c/docs/z.md", "notes.md", "d/notes.md", "specs/s.md"],
+  );
+  let root = &fixture.0;
+  let paths = [
+    root.join("a/docs"),
+    root.join("specs"),
+    root.join("b/docs"),
+    root.join("notes.md"),
+    root.join("a/docs"),
+    root.join("d/notes.md"),
+    root.join("c/docs"),
+  ];
+  assert_eq!(
+    names(&paths),
+    ["docs", "specs", "docs (2)", "notes.md", "notes (2).md", "docs (3)"]
+  );
+
+  let roots = workspace::workspace_roots(&paths);
+  assert_eq!(roots[2].path, root.join("b/docs").to_string_lossy());
+}
+
+#[test]
+fn prefixes_virtual_paths_with_the_root_name() {
+  assert_eq!(workspace::prefixed_virtual_path("docs", "a/b.md"), "docs/a/b.md");
+  assert_eq!(workspace::prefixed_virtual_path("notes.md", ""), "notes.md");
+}
+
+#[test]
+fn prefixed_sink_moves_walked_files_below_the_root() {
+  let fixture = tree_fixture("workspace-sink", &["guide.md", "sub/page.md"]);
+  let collected = CollectSink::default();
+  let sink = PrefixedSink::new("docs (2)", &collected);
+  scan::walk_supported_files(&fixture.0, &ScanOptions::default(), 2, &AtomicBool::new(false), &sink)
+    .expect("scan was not cancelled");
+
+  let files = collected.into_files();
+  assert_eq!(common::virtual_paths(&files), ["docs (2)/guide.md", "docs (2)/sub/page.md"]);
+}
+
+#[test]
+fn limits_are_shared_by_the_roots_of_a_workspace() {
+  let files = ["a/1.md", "a/2.md", "a/3.md", "b/4.md", "b/5.md"];
+  let fixture = tree_fixture("workspace-budget", &files);
+  let options = ScanOptions {
+    max_files: Some(4),
+    max_duration_ms: Some(60_000),
+    ..ScanOptions::default()
+  };
+
+  let first = workspace::remaining_options(&options, 0, Duration::ZERO);
+  let (outcome, a_files) = common::walk(&fixture.0.join("a"), &first, 2);
+  assert_eq!((a_files.len(), outcome.truncated), (3, None));
+
+  let second = workspace::remaining_options(&options, a_files.len(), Duration::from_secs(1));
+  assert_eq!((second.max_files, second.max_duration_ms), (Some(1), Some(59_000)));
+  let (outcome, b_files) = common::walk(&fixture.0.join("b"), &second, 2);
+  assert_eq!((b_files.len(), outcome.truncated), (1, Some(ScanTruncation::MaxFiles)));
+
+  // Once the time is used up, later roots stop at once.
+  let late = workspace::remaining_options(&options, 0, Duration::from_secs(61));
+  let (outcome, _) = common::walk(&fixture.0.join("b"), &late, 2);
+  assert_eq!(outcome.truncated, Some(ScanTruncation::MaxDuration));
+}
@@ -16,13 +16,31 @@ CATEGORY_EXTENSIONS = {
   "mindmap": set(),  # handled via double-extension detection
   "drawio": {".drawio"},
   "pdf": {".pdf"},
-  "word": {".docx"}, # only .docx supported
-  "excel": {".xlsx"}, # only .xlsx supported
+  "word": {".docx", ".docm", ".dotx", ".dotm"}, # OOXML only, no legacy .doc
+  "excel": {".xlsx", ".xlsm", ".xltx", ".xltm"}, # OOXML only, no legacy .xls
   "text": {".txt"},
-  "slides": {".pptx"}, # only .pptx supported
+  "code": {
+    ".rs", ".py", ".pyi", ".pyw", ".js", ".mjs", ".cjs", ".jsx", ".ts", ".mts", ".cts", ".tsx",
+    ".json", ".toml", ".yaml", ".yml", ".ini", ".cfg", ".sh", ".bash", ".zsh", ".ps1", ".c", ".h",
+    ".cc", ".cpp", ".cxx", ".hh", ".hpp", ".hxx", ".cs", ".go", ".java", ".kt", ".kts", ".swift",
+    ".rb", ".php", ".pl", ".pm", ".lua", ".r", ".sql", ".css", ".scss", ".less", ".html", ".htm",
+    ".xml", ".vue", ".graphql", ".gql", ".diff", ".patch", ".mk", ".vb", ".scala", ".dart",
+  },
+  "slides": {".pptx", ".pptm", ".potx", ".potm", ".ppsx", ".ppsm"}, # OOXML only, no legacy .ppt
   "marpit": set(),  # handled via double-extension detection
 }
 
+# Source files known by name rather than extension.
+CODE_FILE_NAMES = {
+  "dockerfile", "containerfile", "makefile", "gnumakefile", "cmakelists.txt", "gemfile",
+  "rakefile", "vagrantfile", ".bashrc", ".bash_profile", ".zshrc", ".profile", ".editorconfig",
+}
+
+# The site's own files at the top of the directory: the page, its assets, this script and the
+# index it writes. Listing them would make every site index its own machinery.
+SITE_FILES = {"index.html", "index.json", "generate.py"}
+SITE_DIRS = {"assets"}
+
 CATEGORY_ORDER = tuple(CATEGORY_EXTENSIONS.keys())
 GroupMap = Dict[str, Dict[str, List[str]]]
 
@@ -33,6 +51,8 @@ def categorize_file(path: Path) -> Optional[str]:
     return None
 
   name_lower = path.name.lower()
+  if name_lower in CODE_FILE_NAMES:
+    return "code"
   if name_lower.endswith(".mm.md"):
     return "mindmap"
   if name_lower.endswith(".ppt.md"):
@@ -46,6 +66,11 @@ def categorize_file(path: Path) -> Optional[str]:
   return None
 
 
+def is_site_file(rel_path: str) -> bool:
+  """Whether a path relative to the site root is part of the site rather than its content."""
+  return rel_path in SITE_FILES or ("/" in rel_path and rel_path.split("/", 1)[0] in SITE_DIRS)
+
+
 def collect_grouped_files(directory: Path) -> Tuple[GroupMap, Dict[str, int]]:
   """Recursively collect supported files grouped by directory and category."""
   grouped: GroupMap = {}
@@ -57,6 +82,8 @@ def collect_grouped_files(directory: Path) -> Tuple[GroupMap, Dict[str, int]]:
       continue
 
     rel_path = path.relative_to(directory).as_posix()
+    if is_site_file(rel_path):
+      continue
     rel_dir = path.parent.relative_to(directory).as_posix()
     dir_key = "." if rel_dir == "." else rel_dir
 
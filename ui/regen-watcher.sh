@@ -5,7 +5,7 @@ WATCH_DIR=${WATCH_DIR:-/workspace}
 TARGET_DIR=${TARGET_DIR:-$WATCH_DIR}
 GENERATE_SCRIPT=${GENERATE_SCRIPT:-$WATCH_DIR/generate.py}
 TEMPLATE_DIR=${TEMPLATE_DIR:-/opt/template}
-SUPPORTED_PATTERN='\.(png|jpg|jpeg|gif|webp|md|drawio|pdf|xlsx|docx|txt|pptx|mp4|webm|ogv|m4v|mp3|wav|m4a|ogg|oga|flac|aac)$'
+SUPPORTED_PATTERN='(\.(png|jpg|jpeg|gif|webp|md|drawio|pdf|xlsx|xlsm|xltx|xltm|docx|docm|dotx|dotm|txt|pptx|pptm|potx|potm|ppsx|ppsm|mp4|webm|ogv|m4v|mp3|wav|m4a|ogg|oga|flac|aac|rs|py|pyi|pyw|js|mjs|cjs|jsx|ts|mts|cts|tsx|json|toml|yaml|yml|ini|cfg|sh|bash|zsh|ps1|c|h|cc|cpp|cxx|hh|hpp|hxx|cs|go|java|kt|kts|swift|rb|php|pl|pm|lua|r|sql|css|scss|less|html|htm|xml|vue|graphql|gql|diff|patch|mk|vb|scala|dart)|^(dockerfile|containerfile|makefile|gnumakefile|gemfile|rakefile|vagrantfile|\.bashrc|\.bash_profile|\.zshrc|\.profile|\.editorconfig))$'
 SITE_NAME=${SITE_NAME:-文件浏览器}
 
 if [[ ! -d "$WATCH_DIR" ]]; then